@@ -227,10 +227,10 @@ fn run() -> Result<()> {
     }
 
     if let Some(ref mut chan) = ts_chan {
-        chan.enable();
+        chan.enable()?;
     }
 
-    sample_chan.enable();
+    sample_chan.enable()?;
 
     // ----- Check for a scale and offset -----
 
@@ -315,7 +315,7 @@ fn run() -> Result<()> {
         // Get the timestamp. Use the time of the _last_ sample.
 
         let ts: u64 = if let Some(ref chan) = ts_chan {
-            buf.channel_iter::<u64>(chan)
+            buf.channel_iter::<u64>(chan)?
                 .nth(n_sample - 1)
                 .unwrap_or_default()
         }
@@ -333,7 +333,7 @@ fn run() -> Result<()> {
         Note: We could do the following to convert each sample, one at a time,
             but it's more efficient to convert the whole buffer using read()
 
-        let data: Vec<RawSampleType> = buf.channel_iter::<RawSampleType>(&sample_chan)
+        let data: Vec<RawSampleType> = buf.channel_iter::<RawSampleType>(&sample_chan)?
                                            .map(|x| sample_chan.convert(x))
                                            .collect();
         */
@@ -71,13 +71,9 @@ fn main() {
     // Note that we just look at unsigned 16-bit samples.
     // This is arbitrary for the purpose of example.
 
-    let mut nchan = 0;
-    for chan in dev.channels() {
-        if chan.type_of() == Some(TypeId::of::<u16>()) {
-            nchan += 1;
-            chan.enable();
-        }
-    }
+    let nchan = dev
+        .enable_channels_where(|chan| chan.type_of() == Some(TypeId::of::<u16>()))
+        .unwrap_or(0);
 
     if nchan == 0 {
         eprintln!("Couldn't find any unsigned 16-bit channels to capture.");
@@ -95,8 +91,13 @@ fn main() {
         process::exit(4);
     }
 
-    for chan in dev.channels() {
-        let data: Vec<u16> = buf.channel_iter::<u16>(&chan).collect();
-        println!("{}: {:?}", chan.id().unwrap_or_default(), data);
+    for chan in dev.channels().filter(|chan| chan.is_enabled()) {
+        match buf.channel_iter::<u16>(&chan) {
+            Ok(iter) => {
+                let data: Vec<u16> = iter.collect();
+                println!("{}: {:?}", chan.id().unwrap_or_default(), data);
+            }
+            Err(err) => eprintln!("{}: {}", chan.id().unwrap_or_default(), err),
+        }
     }
 }
@@ -122,8 +122,8 @@ fn run() -> Result<()> {
         .find_channel(chan_name, false)
         .context(format!("No '{}' channel on this device", chan_name))?;
 
-    ts_chan.enable();
-    sample_chan.enable();
+    ts_chan.enable()?;
+    sample_chan.enable()?;
 
     // ----- Set sample frequency and trigger -----
 
@@ -196,12 +196,12 @@ fn run() -> Result<()> {
 
         // Extract and print the data
 
-        let ts_data = buf.channel_iter::<u64>(&ts_chan);
+        let ts_data = buf.channel_iter::<u64>(&ts_chan)?;
 
         // The timestamp is represented as a 64-bit integer number of
         // nanoseconds since the Unix Epoch. We convert to a Rust SystemTime,
         // then a chrono DataTime for pretty printing.
-        buf.channel_iter::<u16>(&sample_chan)
+        buf.channel_iter::<u16>(&sample_chan)?
             .zip(ts_data.map(|ts| {
                 DateTime::<Utc>::from(SystemTime::UNIX_EPOCH + Duration::from_nanos(ts))
                     .format("%T%.6f")
@@ -0,0 +1,500 @@
+// industrial-io/src/ad9361.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Helpers for the AD9361 family of RF transceivers.
+//!
+//! These layer on top of the `ad9361-phy` control device for the common
+//! chores of configuring the transceiver, like tuning the local
+//! oscillators, setting the gains, loading FIR filters, and running the
+//! calibrations. This is similar in spirit to the C _libad9361_, although
+//! it only covers the attributes of the driver, not the filter design.
+//!
+//! The AD9081 mixed-signal front end is covered by [`Ad9081`], for tuning
+//! the numerically controlled oscillators (NCOs) of its datapaths. It has
+//! no helpers for calibration, since its driver runs the calibrations
+//! itself when the JESD204 link is brought up, and has no attributes to
+//! run them on request. Its gains and filters are set up by the device
+//! tree, rather than at runtime.
+//!
+//! This requires the `ad9361` feature.
+//!
+
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use super::*;
+
+/// The name of the control device for the transceiver
+pub const AD9361_PHY_NAME: &str = "ad9361-phy";
+
+/// The name of the receive device of the AD9081
+pub const AD9081_RX_NAME: &str = "axi-ad9081-rx-hpc";
+
+/// The name of the transmit device of the AD9081
+pub const AD9081_TX_NAME: &str = "axi-ad9081-tx-hpc";
+
+/// The gain control mode of a receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GainControlMode {
+    /// The gain is set by the application.
+    Manual,
+    /// Slow automatic gain control, for continuous signals
+    SlowAttack,
+    /// Fast automatic gain control, for burst signals
+    FastAttack,
+    /// Automatic gain control, with the gain changed by control pins
+    Hybrid,
+}
+
+impl GainControlMode {
+    /// Gets the name used for the mode by the driver, like "slow_attack".
+    pub fn name(&self) -> &'static str {
+        use GainControlMode::*;
+        match *self {
+            Manual => "manual",
+            SlowAttack => "slow_attack",
+            FastAttack => "fast_attack",
+            Hybrid => "hybrid",
+        }
+    }
+}
+
+impl fmt::Display for GainControlMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for GainControlMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        use GainControlMode::*;
+        match s.trim() {
+            "manual" => Ok(Manual),
+            "slow_attack" => Ok(SlowAttack),
+            "fast_attack" => Ok(FastAttack),
+            "hybrid" => Ok(Hybrid),
+            _ => Err(Error::StringConversionError),
+        }
+    }
+}
+
+/// A calibration that can be run by the transceiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CalibrationMode {
+    /// Runs the calibrations automatically, when the LO frequencies change
+    Auto,
+    /// Only runs the calibrations when requested
+    Manual,
+    /// Runs the TX quadrature calibration
+    TxQuad,
+    /// Runs the RF DC offset calibration
+    RfDcOffset,
+    /// Runs the RSSI gain step calibration
+    RssiGainStep,
+}
+
+impl CalibrationMode {
+    /// Gets the name used for the mode by the driver, like "tx_quad".
+    pub fn name(&self) -> &'static str {
+        use CalibrationMode::*;
+        match *self {
+            Auto => "auto",
+            Manual => "manual",
+            TxQuad => "tx_quad",
+            RfDcOffset => "rf_dc_offs",
+            RssiGainStep => "rssi_gain_step",
+        }
+    }
+}
+
+impl fmt::Display for CalibrationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for CalibrationMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        use CalibrationMode::*;
+        match s.trim() {
+            "auto" => Ok(Auto),
+            "manual" => Ok(Manual),
+            "tx_quad" => Ok(TxQuad),
+            "rf_dc_offs" => Ok(RfDcOffset),
+            "rssi_gain_step" => Ok(RssiGainStep),
+            _ => Err(Error::StringConversionError),
+        }
+    }
+}
+
+/// An AD9361 transceiver.
+///
+/// This wraps the `ad9361-phy` control device. The receivers and
+/// transmitters are numbered from zero, as in the `voltageN` channels of
+/// the device.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// let ctx = iio::Context::from_uri("ip:192.168.2.1").unwrap();
+/// let trx = iio::Ad9361::new(&ctx).unwrap();
+///
+/// trx.set_rx_lo_frequency(iio::Hertz(2.4e9)).unwrap();
+/// trx.set_gain_control_mode(0, iio::GainControlMode::SlowAttack).unwrap();
+/// println!("RSSI: {}", trx.rssi(0).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ad9361 {
+    /// The control device for the transceiver
+    phy: Device,
+}
+
+impl Ad9361 {
+    /// Gets the transceiver in the context.
+    pub fn new(ctx: &Context) -> Result<Self> {
+        ctx.find_device(AD9361_PHY_NAME)
+            .map(Self::from_device)
+            .ok_or_else(|| Error::General(format!("Device not found: {}", AD9361_PHY_NAME)))
+    }
+
+    /// Creates a transceiver from its control device.
+    pub fn from_device(phy: Device) -> Self {
+        Self { phy }
+    }
+
+    /// Gets the control device for the transceiver.
+    pub fn phy(&self) -> &Device {
+        &self.phy
+    }
+
+    /// Gets a channel of the control device.
+    fn channel(&self, id: &str, is_output: bool) -> Result<Channel> {
+        self.phy
+            .find_channel(id, is_output)
+            .ok_or_else(|| Error::General(format!("Channel not found: {}", id)))
+    }
+
+    /// Gets the control channel for a receiver.
+    fn rx(&self, rx: usize) -> Result<Channel> {
+        self.channel(&format!("voltage{}", rx), false)
+    }
+
+    /// Gets the control channel for a transmitter.
+    fn tx(&self, tx: usize) -> Result<Channel> {
+        self.channel(&format!("voltage{}", tx), true)
+    }
+
+    // ----- Local Oscillators -----
+
+    /// Gets the frequency of the receive local oscillator.
+    pub fn rx_lo_frequency(&self) -> Result<Hertz> {
+        self.channel("altvoltage0", true)?.attr_read("frequency")
+    }
+
+    /// Sets the frequency of the receive local oscillator.
+    pub fn set_rx_lo_frequency(&self, freq: Hertz) -> Result<()> {
+        self.channel("altvoltage0", true)?.attr_write("frequency", freq)
+    }
+
+    /// Gets the frequency of the transmit local oscillator.
+    pub fn tx_lo_frequency(&self) -> Result<Hertz> {
+        self.channel("altvoltage1", true)?.attr_read("frequency")
+    }
+
+    /// Sets the frequency of the transmit local oscillator.
+    pub fn set_tx_lo_frequency(&self, freq: Hertz) -> Result<()> {
+        self.channel("altvoltage1", true)?.attr_write("frequency", freq)
+    }
+
+    // ----- Sample Rate and Bandwidth -----
+
+    /// Gets the baseband sample rate.
+    pub fn sampling_frequency(&self) -> Result<Hertz> {
+        self.rx(0)?.attr_read("sampling_frequency")
+    }
+
+    /// Sets the baseband sample rate, for both receive and transmit.
+    pub fn set_sampling_frequency(&self, freq: Hertz) -> Result<()> {
+        self.rx(0)?.attr_write("sampling_frequency", freq)
+    }
+
+    /// Gets the RF bandwidth of the receivers.
+    pub fn rx_rf_bandwidth(&self) -> Result<Hertz> {
+        self.rx(0)?.attr_read("rf_bandwidth")
+    }
+
+    /// Sets the RF bandwidth of the receivers.
+    pub fn set_rx_rf_bandwidth(&self, bw: Hertz) -> Result<()> {
+        self.rx(0)?.attr_write("rf_bandwidth", bw)
+    }
+
+    /// Gets the RF bandwidth of the transmitters.
+    pub fn tx_rf_bandwidth(&self) -> Result<Hertz> {
+        self.tx(0)?.attr_read("rf_bandwidth")
+    }
+
+    /// Sets the RF bandwidth of the transmitters.
+    pub fn set_tx_rf_bandwidth(&self, bw: Hertz) -> Result<()> {
+        self.tx(0)?.attr_write("rf_bandwidth", bw)
+    }
+
+    // ----- Gain -----
+
+    /// Gets the gain control mode of a receiver.
+    pub fn gain_control_mode(&self, rx: usize) -> Result<GainControlMode> {
+        self.rx(rx)?.attr_read_str("gain_control_mode")?.parse()
+    }
+
+    /// Sets the gain control mode of a receiver.
+    pub fn set_gain_control_mode(&self, rx: usize, mode: GainControlMode) -> Result<()> {
+        self.rx(rx)?.attr_write_str("gain_control_mode", mode.name())
+    }
+
+    /// Gets the gain of a receiver.
+    pub fn rx_gain(&self, rx: usize) -> Result<Decibels> {
+        self.rx(rx)?.hardware_gain()
+    }
+
+    /// Sets the gain of a receiver.
+    ///
+    /// The receiver must be in the manual gain control mode.
+    pub fn set_rx_gain(&self, rx: usize, gain: Decibels) -> Result<()> {
+        self.rx(rx)?.set_hardware_gain(gain)
+    }
+
+    /// Gets the attenuation of a transmitter.
+    ///
+    /// This is reported by the driver as a negative gain.
+    pub fn tx_attenuation(&self, tx: usize) -> Result<Decibels> {
+        let gain = self.tx(tx)?.hardware_gain()?;
+        Ok(Decibels(-gain.0))
+    }
+
+    /// Sets the attenuation of a transmitter.
+    pub fn set_tx_attenuation(&self, tx: usize, atten: Decibels) -> Result<()> {
+        self.tx(tx)?.attr_write("hardwaregain", Decibels(-atten.0))
+    }
+
+    /// Reads the received signal strength indicator (RSSI) of a receiver.
+    ///
+    /// This is the signal level below full scale.
+    pub fn rssi(&self, rx: usize) -> Result<Decibels> {
+        self.rx(rx)?.attr_read("rssi")
+    }
+
+    // ----- FIR Filters -----
+
+    /// Loads the coefficients for the FIR filters of the transceiver.
+    ///
+    /// The configuration is in the text format read by the driver, as
+    /// produced by the filter design tools. The filters must be enabled
+    /// separately, with [`Ad9361::set_fir_enabled()`].
+    pub fn load_fir_filter(&self, config: &str) -> Result<()> {
+        self.phy.attr_write_str("filter_fir_config", config)
+    }
+
+    /// Loads the coefficients for the FIR filters from a file.
+    pub fn load_fir_filter_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let config = fs::read_to_string(path)?;
+        self.load_fir_filter(&config)
+    }
+
+    /// Determines if the FIR filters of the transceiver are enabled.
+    pub fn is_fir_enabled(&self) -> Result<bool> {
+        if self.phy.has_attr("in_out_voltage_filter_fir_en") {
+            self.phy.attr_read_bool("in_out_voltage_filter_fir_en")
+        }
+        else {
+            self.tx(0)?.attr_read_bool("voltage_filter_fir_en")
+        }
+    }
+
+    /// Enables or disables the receive and transmit FIR filters together.
+    pub fn set_fir_enabled(&self, on: bool) -> Result<()> {
+        if self.phy.has_attr("in_out_voltage_filter_fir_en") {
+            self.phy.attr_write_bool("in_out_voltage_filter_fir_en", on)
+        }
+        else {
+            self.tx(0)?.attr_write_bool("voltage_filter_fir_en", on)
+        }
+    }
+
+    // ----- Calibration -----
+
+    /// Gets the calibration mode of the transceiver.
+    pub fn calibration_mode(&self) -> Result<CalibrationMode> {
+        self.phy.attr_read_str("calib_mode")?.parse()
+    }
+
+    /// Sets the calibration mode of the transceiver.
+    ///
+    /// Setting one of the one-time calibrations, like
+    /// [`CalibrationMode::TxQuad`], runs it immediately.
+    pub fn calibrate(&self, mode: CalibrationMode) -> Result<()> {
+        self.phy.attr_write_str("calib_mode", mode.name())
+    }
+}
+
+impl AsRef<Device> for Ad9361 {
+    fn as_ref(&self) -> &Device {
+        &self.phy
+    }
+}
+
+/// An AD9081 mixed-signal front end.
+///
+/// This wraps the `axi-ad9081-rx-hpc` receive device and, if there is
+/// one, the `axi-ad9081-tx-hpc` transmit device. The datapaths are
+/// numbered from zero, as in the `voltageN_i` channels of the devices.
+/// Each has a main NCO, in its coarse digital converter, and a channel
+/// NCO, in its fine digital converter.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// let ctx = iio::Context::from_uri("ip:192.168.2.1").unwrap();
+/// let mxfe = iio::Ad9081::new(&ctx).unwrap();
+///
+/// mxfe.set_rx_main_nco_frequency(0, iio::Hertz(1.0e9)).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ad9081 {
+    /// The receive device
+    rx: Device,
+    /// The transmit device, if there is one
+    tx: Option<Device>,
+}
+
+impl Ad9081 {
+    /// Gets the front end in the context.
+    pub fn new(ctx: &Context) -> Result<Self> {
+        let rx = ctx
+            .find_device(AD9081_RX_NAME)
+            .ok_or_else(|| Error::General(format!("Device not found: {}", AD9081_RX_NAME)))?;
+        Ok(Self {
+            rx,
+            tx: ctx.find_device(AD9081_TX_NAME),
+        })
+    }
+
+    /// Gets the receive device.
+    pub fn rx_device(&self) -> &Device {
+        &self.rx
+    }
+
+    /// Gets the transmit device, if there is one.
+    pub fn tx_device(&self) -> Option<&Device> {
+        self.tx.as_ref()
+    }
+
+    /// Gets the channel for a receive datapath.
+    fn rx(&self, rx: usize) -> Result<Channel> {
+        let id = format!("voltage{}_i", rx);
+        self.rx
+            .find_channel(&id, false)
+            .ok_or_else(|| Error::General(format!("Channel not found: {}", id)))
+    }
+
+    /// Gets the channel for a transmit datapath.
+    fn tx(&self, tx: usize) -> Result<Channel> {
+        let dev = self
+            .tx
+            .as_ref()
+            .ok_or_else(|| Error::General(format!("Device not found: {}", AD9081_TX_NAME)))?;
+        let id = format!("voltage{}_i", tx);
+        dev.find_channel(&id, true)
+            .ok_or_else(|| Error::General(format!("Channel not found: {}", id)))
+    }
+
+    /// Writes an NCO frequency, which the driver takes in whole hertz.
+    fn write_nco(chan: &Channel, attr: &str, freq: Hertz) -> Result<()> {
+        chan.attr_write(attr, Hertz(freq.0.round()))
+    }
+
+    // ----- Receive NCOs -----
+
+    /// Gets the frequency of the main NCO for a receive datapath.
+    pub fn rx_main_nco_frequency(&self, rx: usize) -> Result<Hertz> {
+        self.rx(rx)?.attr_read("main_nco_frequency")
+    }
+
+    /// Sets the frequency of the main NCO for a receive datapath.
+    pub fn set_rx_main_nco_frequency(&self, rx: usize, freq: Hertz) -> Result<()> {
+        Self::write_nco(&self.rx(rx)?, "main_nco_frequency", freq)
+    }
+
+    /// Gets the frequency of the channel NCO for a receive datapath.
+    pub fn rx_channel_nco_frequency(&self, rx: usize) -> Result<Hertz> {
+        self.rx(rx)?.attr_read("channel_nco_frequency")
+    }
+
+    /// Sets the frequency of the channel NCO for a receive datapath.
+    pub fn set_rx_channel_nco_frequency(&self, rx: usize, freq: Hertz) -> Result<()> {
+        Self::write_nco(&self.rx(rx)?, "channel_nco_frequency", freq)
+    }
+
+    // ----- Transmit NCOs -----
+
+    /// Gets the frequency of the main NCO for a transmit datapath.
+    pub fn tx_main_nco_frequency(&self, tx: usize) -> Result<Hertz> {
+        self.tx(tx)?.attr_read("main_nco_frequency")
+    }
+
+    /// Sets the frequency of the main NCO for a transmit datapath.
+    pub fn set_tx_main_nco_frequency(&self, tx: usize, freq: Hertz) -> Result<()> {
+        Self::write_nco(&self.tx(tx)?, "main_nco_frequency", freq)
+    }
+
+    /// Gets the frequency of the channel NCO for a transmit datapath.
+    pub fn tx_channel_nco_frequency(&self, tx: usize) -> Result<Hertz> {
+        self.tx(tx)?.attr_read("channel_nco_frequency")
+    }
+
+    /// Sets the frequency of the channel NCO for a transmit datapath.
+    pub fn set_tx_channel_nco_frequency(&self, tx: usize, freq: Hertz) -> Result<()> {
+        Self::write_nco(&self.tx(tx)?, "channel_nco_frequency", freq)
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Gain control modes convert to and from the driver names.
+    #[test]
+    fn gain_control_mode_names() {
+        use GainControlMode::*;
+        for mode in &[Manual, SlowAttack, FastAttack, Hybrid] {
+            assert_eq!(mode.name().parse::<GainControlMode>().unwrap(), *mode);
+        }
+        assert!("medium_attack".parse::<GainControlMode>().is_err());
+    }
+
+    // Calibration modes convert to and from the driver names.
+    #[test]
+    fn calibration_mode_names() {
+        use CalibrationMode::*;
+        for mode in &[Auto, Manual, TxQuad, RfDcOffset, RssiGainStep] {
+            assert_eq!(mode.name().parse::<CalibrationMode>().unwrap(), *mode);
+        }
+        assert_eq!("auto\n".parse::<CalibrationMode>().unwrap(), Auto);
+        assert!("rx_quad".parse::<CalibrationMode>().is_err());
+    }
+}
@@ -45,8 +45,7 @@ fn main() {
 
         if dev.is_buffer_capable() {
             for chan in &mut dev.channels() {
-                if chan.is_scan_element() {
-                    chan.enable();
+                if chan.enable().is_ok() {
                     break;
                 }
             }
@@ -34,7 +34,10 @@
 //! [`Device`] object that will be used, with the function
 //! [`Device::create_buffer()`]. This call will fail if no [`Channel`]s have
 //! been enabled, or for triggered buffers, if the trigger has not been
-//! assigned.
+//! assigned. Alternately, [`Device::create_input_buffer()`] and
+//! [`Device::create_output_buffer()`] create buffers whose direction is
+//! checked at compile time, so that an input buffer can't be pushed, and an
+//! output buffer can't be refilled.
 //!
 //! [`Buffer`] objects are automatically dropped when their scope ends.
 //!
@@ -49,33 +52,180 @@
 //! [triggers assigned]: crate::device::Device::set_trigger()
 
 use std::{
+    any::Any,
+    cell::Cell,
     collections::HashMap,
     marker::PhantomData,
     mem,
-    os::raw::{c_int, c_longlong},
+    os::raw::c_int,
     ptr,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
 };
 
 use super::*;
 use crate::ffi;
+use nix::errno::Errno;
 
 /// An Industrial I/O input or output buffer.
 ///
 /// See [here][crate::buffer] for a detailed explanation of how buffers work.
 ///
+/// The direction of the buffer can be specified at compile time with the
+/// type parameter, `D`, which is one of the [`Input`], [`Output`], or
+/// [`AnyDirection`] marker types. Input buffers can only be refilled, and
+/// output buffers can only be pushed. A buffer created with
+/// [`Device::create_buffer()`] can do either, and is checked at runtime
+/// by the C library.
+///
 /// # Examples
 ///
 #[derive(Debug)]
-pub struct Buffer {
+pub struct Buffer<D = AnyDirection> {
     /// The underlying buffer from the C library
     pub(crate) buf: *mut ffi::iio_buffer,
     /// The buffer capacity (# samples from each channel)
     pub(crate) cap: usize,
+    /// Whether the buffer was created in cyclic mode
+    pub(crate) cyclic: bool,
     /// Copy of the device to which this device is attached.
     pub(crate) dev: Device,
+    /// Shared pointer to the C buffer, for cancellation tokens
+    shared: Arc<Mutex<SharedBuf>>,
+    /// Whether to check for overruns after each refill
+    detect_overruns: bool,
+    /// The number of overruns detected
+    overruns: u64,
+    /// The time the buffer was created
+    created: Instant,
+    /// The free space in the kernel queue of an output buffer, from the
+    /// `data_available` attribute, when the buffer was created and the
+    /// queue was empty. See [`Buffer::drain()`].
+    empty_space: Option<i64>,
+    /// Statistics for the transfers to or from the buffer
+    stats: Cell<BufferStats>,
+    /// The application data attached to the buffer
+    user_data: Option<UserData>,
+    /// The direction of the buffer
+    pub(crate) phantom: PhantomData<D>,
+}
+
+/// A pointer to the underlying C buffer that can be shared with other
+/// threads to cancel buffer operations. It is set to NULL when the buffer
+/// is destroyed.
+#[derive(Debug)]
+struct SharedBuf(*mut ffi::iio_buffer);
+
+// The C library allows a buffer to be cancelled from another thread.
+unsafe impl Send for SharedBuf {}
+
+/// A buffer that can only be used for input (capture) from the hardware.
+pub type InputBuffer = Buffer<Input>;
+
+/// A buffer that can only be used for output to the hardware.
+pub type OutputBuffer = Buffer<Output>;
+
+/// A handle to a buffer, of any direction.
+///
+/// This lets the channel functions that move samples in and out of a
+/// buffer, like [`Channel::read()`], accept any [`Buffer`], whatever its
+/// direction type, without another type parameter. It is implemented for
+/// all buffers, and can't be implemented outside of this crate.
+pub trait BufferHandle: sealed::Sealed {
+    /// Gets the raw pointer to the underlying C buffer.
+    fn as_raw(&self) -> *mut ffi::iio_buffer;
+
+    /// Gets the capacity of the buffer, in samples from each channel.
+    fn capacity(&self) -> usize;
+}
+
+impl<D> BufferHandle for Buffer<D> {
+    fn as_raw(&self) -> *mut ffi::iio_buffer {
+        self.buf
+    }
+
+    fn capacity(&self) -> usize {
+        self.cap
+    }
 }
 
-impl Buffer {
+impl<B: BufferHandle + ?Sized> BufferHandle for &B {
+    fn as_raw(&self) -> *mut ffi::iio_buffer {
+        (**self).as_raw()
+    }
+
+    fn capacity(&self) -> usize {
+        (**self).capacity()
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl<D> Sealed for super::Buffer<D> {}
+    impl<B: Sealed + ?Sized> Sealed for &B {}
+}
+
+impl<D> Buffer<D> {
+    /// Creates a new buffer from the underlying C buffer.
+    pub(crate) fn new(buf: *mut ffi::iio_buffer, cap: usize, cyclic: bool, dev: Device) -> Self {
+        let empty_space = output_queue_space(&dev);
+        Self {
+            buf,
+            cap,
+            cyclic,
+            dev,
+            shared: Arc::new(Mutex::new(SharedBuf(buf))),
+            detect_overruns: false,
+            overruns: 0,
+            created: Instant::now(),
+            empty_space,
+            stats: Cell::new(BufferStats::default()),
+            user_data: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a buffer from a raw C buffer pointer.
+    ///
+    /// The buffer takes ownership of the C buffer, and destroys it when
+    /// dropped.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be a valid, non-NULL, buffer for the device, that
+    /// is not owned or destroyed by anything else. The sample count and
+    /// cyclic mode must be the ones used to create it.
+    pub unsafe fn from_raw(
+        dev: &Device,
+        buf: *mut ffi::iio_buffer,
+        sample_count: usize,
+        cyclic: bool,
+    ) -> Self {
+        Self::new(buf, sample_count, cyclic, dev.clone())
+    }
+
+    /// Gets the raw pointer to the underlying C buffer.
+    ///
+    /// This can be passed to other C libraries that work with IIO buffers.
+    /// It remains owned by this object.
+    pub fn as_raw(&self) -> *mut ffi::iio_buffer {
+        self.buf
+    }
+
+    /// Consumes the buffer, returning the raw pointer to the underlying
+    /// C buffer.
+    ///
+    /// The C buffer is _not_ destroyed. The caller becomes responsible for
+    /// destroying it, perhaps with [`Buffer::from_raw()`]. Any cancel
+    /// tokens for the buffer will no longer work, and any user data is
+    /// dropped.
+    pub fn into_raw(mut self) -> *mut ffi::iio_buffer {
+        self.clear_user_data();
+        mem::replace(&mut self.buf, ptr::null_mut())
+    }
+
     /// Get the buffer size.
     ///
     /// Get the buffer capacity in number of samples from each channel that
@@ -84,11 +234,69 @@ impl Buffer {
         self.cap
     }
 
+    /// Determines if the buffer was created in cyclic mode.
+    ///
+    /// In cyclic mode, the samples of an output buffer are pushed to the
+    /// hardware once, and then repeated by the hardware indefinitely.
+    pub fn is_cyclic(&self) -> bool {
+        self.cyclic
+    }
+
+    /// Gets the step size of the buffer, in bytes.
+    ///
+    /// This is the distance between two consecutive samples of a single
+    /// channel in the buffer.
+    pub fn step(&self) -> usize {
+        unsafe { ffi::iio_buffer_step(self.buf) as usize }
+    }
+
+    /// Gets the size of a single sample set in the buffer, in bytes.
+    ///
+    /// This is the number of bytes required to hold one sample from each
+    /// of the enabled channels, including any padding needed for alignment.
+    pub fn sample_size(&self) -> Result<usize> {
+        self.dev.sample_size()
+    }
+
+    /// Gets the offset, in bytes, of the first sample for a channel from
+    /// the start of the buffer.
+    pub fn channel_offset(&self, chan: &Channel) -> usize {
+        unsafe {
+            let begin = ffi::iio_buffer_start(self.buf) as usize;
+            let first = ffi::iio_buffer_first(self.buf, chan.chan) as usize;
+            first.saturating_sub(begin)
+        }
+    }
+
     /// Gets a reference to the device to which this buffer is attached.
     pub fn device(&self) -> &Device {
         &self.dev
     }
 
+    /// Attaches application data to the buffer.
+    ///
+    /// Any previous data is dropped. The data is held until it is
+    /// replaced, cleared, or the buffer is destroyed.
+    ///
+    /// As for [`Device::set_user_data()`], the data is kept by this crate,
+    /// apart from the data pointer of the C buffer.
+    pub fn set_user_data<T: Any + Send + Sync>(&mut self, data: T) {
+        self.user_data = Some(Arc::new(data));
+    }
+
+    /// Gets the application data attached to the buffer.
+    ///
+    /// This returns `None` if there is no data, or if it is not of type
+    /// `T`.
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.user_data.as_ref()?.downcast_ref()
+    }
+
+    /// Removes and drops the application data attached to the buffer.
+    pub fn clear_user_data(&mut self) {
+        self.user_data = None;
+    }
+
     /// Gets a pollable file descriptor for the buffer.
     ///
     /// This can be used to determine when [`Buffer::refill()`] or
@@ -107,32 +315,6 @@ impl Buffer {
         sys_result(ret, ())
     }
 
-    /// Fetch more samples from the hardware.
-    ///
-    /// This is only valid for input buffers.
-    pub fn refill(&mut self) -> Result<usize> {
-        let ret = unsafe { ffi::iio_buffer_refill(self.buf) };
-        sys_result(ret as i32, ret as usize)
-    }
-
-    /// Send the samples to the hardware.
-    ///
-    /// This is only valid for output buffers.
-    pub fn push(&self) -> Result<usize> {
-        let ret = unsafe { ffi::iio_buffer_push(self.buf) };
-        sys_result(ret as i32, ret as usize)
-    }
-
-    /// Send a given number of samples to the hardware.
-    ///
-    /// This is only valid for output buffers. Note that the number of samples
-    /// explicitly doesn't refer to their size in bytes, but the actual number
-    /// of samples, regardless of the sample size in memory.
-    pub fn push_partial(&self, num_samples: usize) -> Result<usize> {
-        let ret = unsafe { ffi::iio_buffer_push_partial(self.buf, num_samples) };
-        sys_result(ret as i32, ret as usize)
-    }
-
     /// Cancel all buffer operations.
     ///
     /// This function cancels all outstanding [`Buffer`] operations
@@ -159,112 +341,85 @@ impl Buffer {
         }
     }
 
+    /// Gets a token that can be used to cancel the buffer operations from
+    /// another thread.
+    ///
+    /// A thread that is blocked in [`refill()`](Buffer::refill) holds a
+    /// mutable reference to the buffer, so [`cancel()`](Buffer::cancel)
+    /// can't be called on it from elsewhere. The token can be cloned and
+    /// sent to other threads to do so. It remains safe to use after the
+    /// buffer is dropped, at which point cancelling has no effect.
+    pub fn cancel_token(&self) -> CancelToken {
+        CancelToken {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
     /// Determines if the device has any buffer-specific attributes
     pub fn has_attrs(&self) -> bool {
-        unsafe { ffi::iio_device_get_buffer_attrs_count(self.dev.dev) > 0 }
+        self.dev.has_buffer_attrs()
     }
 
     /// Gets the number of buffer-specific attributes
     pub fn num_attrs(&self) -> usize {
-        unsafe { ffi::iio_device_get_buffer_attrs_count(self.dev.dev) as usize }
+        self.dev.num_buffer_attrs()
     }
 
     /// Gets the name of the buffer-specific attribute at the index
     pub fn get_attr(&self, idx: usize) -> Result<String> {
-        let pstr = unsafe { ffi::iio_device_get_buffer_attr(self.dev.dev, idx as c_uint) };
-        cstring_opt(pstr).ok_or(Error::InvalidIndex)
+        self.dev.get_buffer_attr(idx)
     }
 
     /// Try to find a buffer-specific attribute by its name
     pub fn find_attr(&self, name: &str) -> Option<String> {
-        let cname = cstring_or_bail!(name);
-        let pstr = unsafe { ffi::iio_device_find_buffer_attr(self.dev.dev, cname.as_ptr()) };
-        cstring_opt(pstr)
+        self.dev.find_buffer_attr(name)
     }
 
     /// Determines if a buffer-specific attribute exists
     pub fn has_attr(&self, name: &str) -> bool {
-        let cname = cstring_or_bail_false!(name);
-        let pstr = unsafe { ffi::iio_device_find_buffer_attr(self.dev.dev, cname.as_ptr()) };
-        !pstr.is_null()
+        self.dev.has_buffer_attr(name)
     }
 
     /// Reads a buffer-specific attribute
     ///
     /// `attr` The name of the attribute
     pub fn attr_read<T: FromAttribute>(&self, attr: &str) -> Result<T> {
-        let sval = self.attr_read_str(attr)?;
-        T::from_attr(&sval)
+        self.dev.buffer_attr_read(attr)
     }
 
     /// Reads a buffer-specific attribute as a string
     ///
     /// `attr` The name of the attribute
     pub fn attr_read_str(&self, attr: &str) -> Result<String> {
-        let mut buf = vec![0 as c_char; ATTR_BUF_SIZE];
-        let attr = CString::new(attr)?;
-        let ret = unsafe {
-            ffi::iio_device_buffer_attr_read(
-                self.dev.dev,
-                attr.as_ptr(),
-                buf.as_mut_ptr(),
-                buf.len(),
-            )
-        };
-        sys_result(ret as i32, ())?;
-        let s = unsafe {
-            CStr::from_ptr(buf.as_ptr())
-                .to_str()
-                .map_err(|_| Error::StringConversionError)?
-        };
-        Ok(s.into())
+        self.dev.buffer_attr_read_str(attr)
     }
 
     /// Reads a buffer-specific attribute as a boolean
     ///
     /// `attr` The name of the attribute
     pub fn attr_read_bool(&self, attr: &str) -> Result<bool> {
-        let mut val: bool = false;
-        let attr = CString::new(attr)?;
-        let ret =
-            unsafe { ffi::iio_device_buffer_attr_read_bool(self.dev.dev, attr.as_ptr(), &mut val) };
-        sys_result(ret, val)
+        self.dev.buffer_attr_read_bool(attr)
     }
 
     /// Reads a buffer-specific attribute as an integer (i64)
     ///
     /// `attr` The name of the attribute
     pub fn attr_read_int(&self, attr: &str) -> Result<i64> {
-        let mut val: c_longlong = 0;
-        let attr = CString::new(attr)?;
-        let ret = unsafe {
-            ffi::iio_device_buffer_attr_read_longlong(self.dev.dev, attr.as_ptr(), &mut val)
-        };
-        sys_result(ret, val as i64)
+        self.dev.buffer_attr_read_int(attr)
     }
 
     /// Reads a buffer-specific attribute as a floating-point (f64) number
     ///
     /// `attr` The name of the attribute
     pub fn attr_read_float(&self, attr: &str) -> Result<f64> {
-        let mut val: f64 = 0.0;
-        let attr = CString::new(attr)?;
-        let ret = unsafe {
-            ffi::iio_device_buffer_attr_read_double(self.dev.dev, attr.as_ptr(), &mut val)
-        };
-        sys_result(ret, val)
+        self.dev.buffer_attr_read_float(attr)
     }
 
     /// Reads all the buffer-specific attributes.
     /// This is especially useful when using the network backend to
     /// retrieve all the attributes with a single call.
     pub fn attr_read_all(&self) -> Result<HashMap<String, String>> {
-        let mut map = HashMap::new();
-        let pmap = (&mut map as *mut HashMap<_, _>).cast();
-        let ret = unsafe {
-            ffi::iio_device_buffer_attr_read_all(self.dev.dev, Some(attr_read_all_cb), pmap)
-        };
-        sys_result(ret, map)
+        self.dev.buffer_attr_read_all()
     }
 
     /// Writes a buffer-specific attribute
@@ -272,8 +427,7 @@ impl Buffer {
     /// `attr` The name of the attribute
     /// `val` The value to write
     pub fn attr_write<T: ToAttribute>(&self, attr: &str, val: T) -> Result<()> {
-        let sval = T::to_attr(&val)?;
-        self.attr_write_str(attr, &sval)
+        self.dev.buffer_attr_write(attr, val)
     }
 
     /// Writes a buffer-specific attribute as a string
@@ -281,12 +435,7 @@ impl Buffer {
     /// `attr` The name of the attribute
     /// `val` The value to write
     pub fn attr_write_str(&self, attr: &str, val: &str) -> Result<()> {
-        let attr = CString::new(attr)?;
-        let sval = CString::new(val)?;
-        let ret = unsafe {
-            ffi::iio_device_buffer_attr_write(self.dev.dev, attr.as_ptr(), sval.as_ptr())
-        };
-        sys_result(ret as i32, ())
+        self.dev.buffer_attr_write_str(attr, val)
     }
 
     /// Writes a buffer-specific attribute as a boolean
@@ -294,10 +443,7 @@ impl Buffer {
     /// `attr` The name of the attribute
     /// `val` The value to write
     pub fn attr_write_bool(&self, attr: &str, val: bool) -> Result<()> {
-        let attr = CString::new(attr)?;
-        let ret =
-            unsafe { ffi::iio_device_buffer_attr_write_bool(self.dev.dev, attr.as_ptr(), val) };
-        sys_result(ret, ())
+        self.dev.buffer_attr_write_bool(attr, val)
     }
 
     /// Writes a buffer-specific attribute as an integer (i64)
@@ -305,10 +451,7 @@ impl Buffer {
     /// `attr` The name of the attribute
     /// `val` The value to write
     pub fn attr_write_int(&self, attr: &str, val: i64) -> Result<()> {
-        let attr = CString::new(attr)?;
-        let ret =
-            unsafe { ffi::iio_device_buffer_attr_write_longlong(self.dev.dev, attr.as_ptr(), val) };
-        sys_result(ret, ())
+        self.dev.buffer_attr_write_int(attr, val)
     }
 
     /// Writes a buffer-specific attribute as a floating-point (f64) number
@@ -316,98 +459,1702 @@ impl Buffer {
     /// `attr` The name of the attribute
     /// `val` The value to write
     pub fn attr_write_float(&self, attr: &str, val: f64) -> Result<()> {
-        let attr = CString::new(attr)?;
-        let ret =
-            unsafe { ffi::iio_device_buffer_attr_write_double(self.dev.dev, attr.as_ptr(), val) };
-        sys_result(ret, ())
+        self.dev.buffer_attr_write_float(attr, val)
     }
 
     /// Gets an iterator for the buffer attributes in the device
-    pub fn attributes(&self) -> AttrIterator {
+    pub fn attributes(&self) -> AttrIterator<D> {
         AttrIterator { buf: self, idx: 0 }
     }
 
+    /// Gets the watermark for the buffer.
+    ///
+    /// This is the number of samples the kernel waits for before it marks
+    /// the buffer as readable for `poll()`, as read from the `watermark`
+    /// buffer attribute of the device. See
+    /// [`Device::set_buffer_watermark()`].
+    pub fn watermark(&self) -> Result<usize> {
+        let n = self.attr_read_int("watermark")?;
+        Ok(n as usize)
+    }
+
     /// Set the number of kernel buffers for the device.
+    ///
+    /// Note that this only takes effect for buffers created after the call.
+    /// See [`Device::set_num_kernel_buffers()`].
     pub fn set_num_kernel_buffers(&self, n: u32) -> Result<()> {
-        let ret = unsafe { ffi::iio_device_set_kernel_buffers_count(self.dev.dev, n as c_uint) };
-        sys_result(ret, ())
+        self.dev.set_num_kernel_buffers(n)
     }
 
-    /// Gets an iterator for the data from a channel.
-    pub fn channel_iter<T>(&self, chan: &Channel) -> IntoIter<T> {
+    /// Gets the statistics for the transfers to or from the buffer.
+    ///
+    /// These are accumulated over all the successful refills or pushes
+    /// since the buffer was created, or since they were last reset.
+    pub fn stats(&self) -> BufferStats {
+        let mut stats = self.stats.get();
+        stats.elapsed = self.created.elapsed();
+        stats
+    }
+
+    /// Resets the transfer statistics for the buffer.
+    pub fn reset_stats(&mut self) {
+        self.created = Instant::now();
+        self.stats.set(BufferStats::default());
+    }
+
+    /// Runs a transfer operation, recording its statistics if it succeeds.
+    ///
+    /// The transfer returns the number of bytes moved, if any.
+    fn timed<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Copy + Into<Option<usize>>,
+        F: FnOnce() -> Result<T>,
+    {
+        let start = Instant::now();
+        let res = f()?;
+        if let Some(n) = res.into() {
+            let mut stats = self.stats.get();
+            stats.record(n, self.step(), start.elapsed());
+            self.stats.set(stats);
+        }
+        Ok(res)
+    }
+
+    /// Gets the raw contents of the buffer as a slice of bytes.
+    ///
+    /// This is the whole region of the buffer, from the start to the end,
+    /// with the samples of all the enabled channels interleaved in the
+    /// format used by the hardware.
+    pub fn as_bytes(&self) -> &[u8] {
+        let (ptr, n) = self.byte_region();
+        unsafe { slice::from_raw_parts(ptr, n) }
+    }
+
+    /// Gets the raw contents of the buffer as a mutable slice of bytes.
+    ///
+    /// This can be used to fill an output buffer with data that is already
+    /// in the format used by the hardware.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        let (ptr, n) = self.byte_region();
+        unsafe { slice::from_raw_parts_mut(ptr, n) }
+    }
+
+    /// Gets the start and length, in bytes, of the buffer memory.
+    fn byte_region(&self) -> (*mut u8, usize) {
+        unsafe {
+            let begin = ffi::iio_buffer_start(self.buf) as usize;
+            let end = ffi::iio_buffer_end(self.buf) as usize;
+            (begin as *mut u8, end.saturating_sub(begin))
+        }
+    }
+
+    /// Checks that the size of the sample type, `T`, matches the size of
+    /// the samples for the channel, as reported in its data format.
+    pub(crate) fn check_sample_size<T>(chan: &Channel) -> Result<()> {
+        let sz_item = mem::size_of::<T>();
+        let sz_chan = chan.data_format().byte_length();
+        if sz_item == 0 || sz_item != sz_chan {
+            return Err(Error::WrongSampleSize(sz_item, sz_chan));
+        }
+        Ok(())
+    }
+
+    /// Gets the location and number of samples for a channel, if the data
+    /// for the channel is held contiguously in the buffer.
+    ///
+    /// This is only the case when the channel is the only one enabled in
+    /// the buffer, and the sample type matches its size.
+    fn channel_contiguous<T>(&self, chan: &Channel) -> Result<(*mut T, usize)> {
+        Self::check_sample_size::<T>(chan)?;
+        let sz_item = mem::size_of::<T>();
+
         unsafe {
-            let begin = ffi::iio_buffer_first(self.buf, chan.chan).cast();
-            let end = ffi::iio_buffer_end(self.buf) as *const T;
-            let ptr = begin;
-            let step: isize = ffi::iio_buffer_step(self.buf) / mem::size_of::<T>() as isize;
-
-            IntoIter {
-                phantom: PhantomData,
-                ptr,
-                end,
-                step,
+            if ffi::iio_buffer_step(self.buf) != sz_item as isize {
+                return Err(Error::NotContiguous);
             }
+            let begin = ffi::iio_buffer_first(self.buf, chan.chan) as usize;
+            let end = ffi::iio_buffer_end(self.buf) as usize;
+            let n = end.saturating_sub(begin) / sz_item;
+            Ok((begin as *mut T, n))
         }
     }
-}
 
-/// Destroy the underlying buffer when the object scope ends.
-impl Drop for Buffer {
-    fn drop(&mut self) {
-        unsafe { ffi::iio_buffer_destroy(self.buf) }
+    /// Gets a slice of the data for a channel.
+    ///
+    /// This is only possible when the channel's samples are held
+    /// contiguously in the buffer, which is when it is the only channel
+    /// enabled for the device. The size of `T` must match the size of a
+    /// sample for the channel.
+    pub fn channel_slice<T>(&self, chan: &Channel) -> Result<&[T]> {
+        let (ptr, n) = self.channel_contiguous::<T>(chan)?;
+        Ok(unsafe { slice::from_raw_parts(ptr, n) })
     }
-}
 
-/// An iterator that moves channel data out of a buffer.
-#[derive(Debug)]
-pub struct IntoIter<T> {
-    phantom: PhantomData<T>,
-    // Pointer to the current sample for a channel
-    ptr: *const T,
-    // Pointer to the end of the buffer
-    end: *const T,
-    // The offset to the next sample for the channel
-    step: isize,
-}
+    /// Gets a mutable slice of the data for a channel.
+    ///
+    /// This has the same restrictions as [`Buffer::channel_slice()`]. It
+    /// can be used to fill an output buffer before pushing it to the
+    /// hardware.
+    pub fn channel_slice_mut<T>(&mut self, chan: &Channel) -> Result<&mut [T]> {
+        let (ptr, n) = self.channel_contiguous::<T>(chan)?;
+        Ok(unsafe { slice::from_raw_parts_mut(ptr, n) })
+    }
 
-impl<T> Iterator for IntoIter<T> {
-    type Item = T;
+    /// Gets a slice of the data for a channel, using a plain-old-data type.
+    ///
+    /// This has the same restrictions as [`Buffer::channel_slice()`], but
+    /// the `Pod` bound on the type, along with a check of the alignment of
+    /// the buffer memory, makes the cast from the raw bytes provably safe.
+    #[cfg(feature = "bytemuck")]
+    pub fn channel_slice_pod<T: bytemuck::Pod>(&self, chan: &Channel) -> Result<&[T]> {
+        let (ptr, n) = self.channel_contiguous::<T>(chan)?;
+        let bytes = unsafe { slice::from_raw_parts(ptr.cast::<u8>(), n * mem::size_of::<T>()) };
+        bytemuck::try_cast_slice(bytes).map_err(Error::PodCast)
+    }
+
+    /// Gets a mutable slice of the data for a channel, using a
+    /// plain-old-data type.
+    ///
+    /// This is the mutable version of [`Buffer::channel_slice_pod()`].
+    #[cfg(feature = "bytemuck")]
+    pub fn channel_slice_pod_mut<T: bytemuck::Pod>(&mut self, chan: &Channel) -> Result<&mut [T]> {
+        let (ptr, n) = self.channel_contiguous::<T>(chan)?;
+        let bytes =
+            unsafe { slice::from_raw_parts_mut(ptr.cast::<u8>(), n * mem::size_of::<T>()) };
+        bytemuck::try_cast_slice_mut(bytes).map_err(Error::PodCast)
+    }
+
+    /// Gets the contents of the buffer as a slice of scan structs.
+    ///
+    /// Each item of the slice is one complete sample set (scan) from the
+    /// buffer, holding a sample for each of the enabled channels. The type
+    /// `T` would normally be a `#[repr(C)]` struct with a field for each
+    /// channel, laid out in the same way as the hardware, including any
+    /// padding. The size of `T` must match the step of the buffer.
+    #[cfg(feature = "bytemuck")]
+    pub fn scan_slice<T: bytemuck::Pod>(&self) -> Result<&[T]> {
+        self.check_scan_size::<T>()?;
+        bytemuck::try_cast_slice(self.as_bytes()).map_err(Error::PodCast)
+    }
+
+    /// Gets the contents of the buffer as a mutable slice of scan structs.
+    ///
+    /// This is the mutable version of [`Buffer::scan_slice()`]. It can be
+    /// used to fill an output buffer, one sample set at a time.
+    #[cfg(feature = "bytemuck")]
+    pub fn scan_slice_mut<T: bytemuck::Pod>(&mut self) -> Result<&mut [T]> {
+        self.check_scan_size::<T>()?;
+        bytemuck::try_cast_slice_mut(self.as_bytes_mut()).map_err(Error::PodCast)
+    }
+
+    /// Checks that the size of the scan type, `T`, matches the step of
+    /// the buffer.
+    #[cfg(feature = "bytemuck")]
+    fn check_scan_size<T>(&self) -> Result<()> {
+        let sz_item = mem::size_of::<T>();
+        let step = self.step();
+        if sz_item == 0 || sz_item != step {
+            return Err(Error::WrongSampleSize(sz_item, step));
+        }
+        Ok(())
+    }
+
+    /// Gets an iterator for the data from a channel.
+    ///
+    /// The size of the sample type, `T`, must match the size of the samples
+    /// for the channel, including any repeats, otherwise an error is
+    /// returned. For a channel with repeated elements, `T` can be an array,
+    /// like `[i16; 3]`, or see [`Buffer::channel_repeat_iter()`].
+    pub fn channel_iter<T>(&self, chan: &Channel) -> Result<IntoIter<T>> {
+        Self::check_sample_size::<T>(chan)?;
+        Ok(unsafe { self.channel_iter_unchecked(chan) })
+    }
+
+    /// Gets an iterator for the data from a channel, without checking
+    /// the sample type.
+    ///
+    /// # Safety
+    ///
+    /// The size of the sample type, `T`, must match the size of the samples
+    /// for the channel, otherwise the data returned by the iterator will be
+    /// garbage, and might be read from outside of the buffer.
+    pub unsafe fn channel_iter_unchecked<T>(&self, chan: &Channel) -> IntoIter<T> {
+        let (ptr, len, step) = self.channel_span(chan);
 
-    fn next(&mut self) -> Option<T> {
+        IntoIter {
+            phantom: PhantomData,
+            ptr,
+            len,
+            step,
+            swap_len: 0,
+        }
+    }
+
+    /// Gets an iterator for the data from a channel, in host byte order.
+    ///
+    /// This is the same as [`Buffer::channel_iter()`], except that each
+    /// sample is byte-swapped if the endianness of the channel, as given by
+    /// its data format, differs from that of the host. For channels with a
+    /// repeat count greater than one, each element of the sample is swapped
+    /// separately. No shift or mask is applied to the values.
+    pub fn channel_iter_host<T>(&self, chan: &Channel) -> Result<IntoIter<T>> {
+        let mut iter = self.channel_iter(chan)?;
+        let fmt = chan.data_format();
+        if fmt.is_big_endian() != cfg!(target_endian = "big") {
+            iter.swap_len = (fmt.length() / 8) as usize;
+        }
+        Ok(iter)
+    }
+
+    /// Gets the location of the first sample for a channel, the number of
+    /// samples in the buffer, and the offset from one sample to the next,
+    /// in bytes.
+    ///
+    /// The offset is kept in bytes since it isn't necessarily a multiple of
+    /// the sample size, such as for a repeat channel that is followed by a
+    /// timestamp.
+    fn channel_span(&self, chan: &Channel) -> (*mut u8, usize, isize) {
         unsafe {
-            if self.ptr.cast() >= self.end {
-                None
-            }
-            else {
-                let prev = self.ptr;
-                self.ptr = self.ptr.offset(self.step);
-                Some(ptr::read(prev))
+            let first = ffi::iio_buffer_first(self.buf, chan.chan) as usize;
+            let end = ffi::iio_buffer_end(self.buf) as usize;
+            let step = ffi::iio_buffer_step(self.buf) as usize;
+
+            let len = match step {
+                0 => 0,
+                _ => (end.saturating_sub(first) + step - 1) / step,
+            };
+            (first as *mut u8, len, step as isize)
+        }
+    }
+
+    /// Gets an iterator over the sample sets (frames) in the buffer.
+    ///
+    /// Each item is a [`Frame`] that gives access to the sample of each
+    /// enabled channel taken at the same index in the buffer. This is
+    /// useful to get time-aligned values from interleaved captures, such
+    /// as a set of voltages along with their timestamp.
+    pub fn frames(&self) -> FrameIter<D> {
+        let (ptr, n) = self.byte_region();
+        let step = self.step();
+        let len = n.checked_div(step).unwrap_or(0);
+
+        FrameIter {
+            buf: self,
+            ptr,
+            len,
+            step,
+        }
+    }
+
+    /// Gets an iterator over chunks of sample sets in the buffer.
+    ///
+    /// Each [`Chunk`] is a view of `n` consecutive sample sets (frames)
+    /// from the buffer, except possibly the last one, which holds whatever
+    /// is left over if the number of sample sets isn't a multiple of `n`.
+    /// This is useful for block-based processing, such as FFT windows or
+    /// filters, without copying the data out of the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn chunks(&self, n: usize) -> Chunks<D> {
+        assert!(n != 0, "chunk size must be non-zero");
+        let frames = self.frames();
+
+        Chunks {
+            buf: self,
+            ptr: frames.ptr,
+            len: frames.len,
+            step: frames.step,
+            chunk_len: n,
+        }
+    }
+
+    /// Gets an iterator over the scaled values of the samples from a
+    /// channel.
+    ///
+    /// Each raw sample is decoded from the hardware format, then has the
+    /// channel's `offset` and `scale` attributes applied to it, as
+    /// `(raw + offset) * scale`, to give a physical value. The attributes
+    /// are read once, when the iterator is created. For channels with a
+    /// repeat count greater than one, only the first element of each
+    /// sample is used.
+    pub fn scaled_iter(&self, chan: &Channel) -> Result<ScaledIter> {
+        let (scale, offset) = chan.scale_offset()?;
+        let (ptr, len, step) = self.channel_span(chan);
+
+        Ok(ScaledIter {
+            phantom: PhantomData,
+            fmt: chan.data_format(),
+            ptr,
+            len,
+            step: step as usize,
+            scale,
+            offset,
+        })
+    }
+
+    /// Gets an iterator over the samples from a channel, with the sample
+    /// type determined at runtime.
+    ///
+    /// Each raw sample is decoded from the hardware format into a
+    /// [`SampleValue`] having an integer type that matches the size and
+    /// sign of the channel's data format. For channels with a repeat count
+    /// greater than one, only the first element of each sample is used.
+    ///
+    /// This returns a `WrongDataType` error if the elements of the data
+    /// format can't be decoded, because they're empty or longer than 64
+    /// bits.
+    pub fn dyn_channel_iter(&self, chan: &Channel) -> Result<DynIter> {
+        let fmt = chan.data_format();
+        if !fmt.is_decodable() {
+            return Err(Error::WrongDataType);
+        }
+        let (ptr, len, step) = self.channel_span(chan);
+
+        Ok(DynIter {
+            phantom: PhantomData,
+            fmt,
+            ptr,
+            len,
+            step: step as usize,
+        })
+    }
+
+    /// Demultiplexes the data for all the enabled channels in the buffer.
+    ///
+    /// This extracts the samples for every enabled scan element of the
+    /// device into a separate vector, keyed by the channel ID. Only the
+    /// channels having a sample size that matches the size of `T` are
+    /// included. The others are skipped, and can be read separately with
+    /// a different sample type.
+    pub fn read_all<T>(&self) -> HashMap<String, Vec<T>> {
+        let sz_item = mem::size_of::<T>();
+
+        self.dev
+            .scan_elements()
+            .filter(|chan| chan.is_enabled() && chan.data_format().byte_length() == sz_item)
+            .map(|chan| {
+                let data = unsafe { self.channel_iter_unchecked::<T>(&chan) }.collect();
+                (chan.id().unwrap_or_default(), data)
+            })
+            .collect()
+    }
+
+    /// Copies the data for a set of channels into a two-dimensional array.
+    ///
+    /// Each row of the array holds the samples from one of the channels,
+    /// in the order that they are given, and each column is a sample index
+    /// in the buffer, giving a (channels x samples) array. The samples are
+    /// converted to host byte order, as with [`Buffer::channel_iter_host()`].
+    ///
+    /// All the channels must be enabled, and the size of `T` must match
+    /// the sample size of each of them, otherwise an error is returned.
+    #[cfg(feature = "ndarray")]
+    pub fn to_array2<T>(&self, chans: &[Channel]) -> Result<ndarray::Array2<T>> {
+        let mut data = Vec::new();
+        let mut nsamples = 0;
+
+        for chan in chans {
+            if !chan.is_enabled() {
+                return Err(Error::ChannelNotEnabled);
             }
+            let iter = self.channel_iter_host::<T>(chan)?;
+            nsamples = iter.len();
+            data.extend(iter);
         }
+        Ok(ndarray::Array2::from_shape_vec((chans.len(), nsamples), data)?)
     }
-}
 
-/// Iterator over the buffer attributes
-/// 'a Lifetime of the Buffer
-#[derive(Debug)]
-pub struct AttrIterator<'a> {
-    /// Reference to the Buffer that we're scanning for attributes
-    buf: &'a Buffer,
-    /// Index to the next Buffer attribute from the iterator
-    idx: usize,
+    /// Calls a function for each sample in the buffer.
+    ///
+    /// This uses the demultiplexing logic of the C library to walk through
+    /// the buffer, sample by sample, calling the function with the channel
+    /// for the sample and the raw bytes of the sample (in the hardware
+    /// format). It returns the total number of bytes processed.
+    pub fn for_each_sample<F>(&self, f: F) -> Result<usize>
+    where
+        F: FnMut(&Channel, &[u8]),
+    {
+        let mut data = (f, self.dev.context());
+        let pdata = (&mut data as *mut (F, Context)).cast();
+        let ret = unsafe {
+            ffi::iio_buffer_foreach_sample(self.buf, Some(foreach_sample_cb::<F>), pdata)
+        };
+        sys_result(ret as i32, ret as usize)
+    }
+
+    /// Gets an iterator over references to the data from a channel.
+    ///
+    /// Unlike [`Buffer::channel_iter()`], this doesn't copy the samples out
+    /// of the buffer, and can be used to make repeated passes over the data
+    /// from the same refill.
+    ///
+    /// The size of the sample type, `T`, must match the size of the samples
+    /// for the channel, otherwise an error is returned.
+    pub fn channel_ref_iter<T>(&self, chan: &Channel) -> Result<Iter<T>> {
+        Self::check_sample_size::<T>(chan)?;
+        let (ptr, len, step) = self.channel_span(chan);
+        check_aligned::<T>(ptr, step as usize)?;
+
+        Ok(Iter {
+            phantom: PhantomData,
+            ptr,
+            len,
+            step,
+        })
+    }
+
+    /// Gets an iterator over the repeated elements of the samples from a
+    /// channel.
+    ///
+    /// Some channels have a repeat count greater than one in their data
+    /// format, meaning that each sample is made up of a number of elements
+    /// of the same type, such as the X, Y, and Z values of a sensor. Each
+    /// item from this iterator is a slice of the elements for one sample.
+    ///
+    /// The size of the element type, `T`, must match the size of a single
+    /// element for the channel, otherwise an error is returned.
+    pub fn channel_repeat_iter<T>(&self, chan: &Channel) -> Result<RepeatIter<T>> {
+        let fmt = chan.data_format();
+        let sz_item = mem::size_of::<T>();
+        let sz_elem = (fmt.length() / 8) as usize;
+        if sz_item == 0 || sz_item != sz_elem {
+            return Err(Error::WrongSampleSize(sz_item, sz_elem));
+        }
+
+        let (ptr, len, step) = self.channel_span(chan);
+        check_aligned::<T>(ptr, step as usize)?;
+
+        Ok(RepeatIter {
+            phantom: PhantomData,
+            ptr,
+            len,
+            step,
+            repeat: fmt.repeat().max(1) as usize,
+        })
+    }
+
+    /// Gets a mutable iterator for the data to a channel.
+    ///
+    /// This is typically used to fill an output buffer with samples for the
+    /// channel before sending them to the hardware with [`Buffer::push()`].
+    ///
+    /// The size of the sample type, `T`, must match the size of the samples
+    /// for the channel, otherwise an error is returned.
+    pub fn channel_iter_mut<T>(&mut self, chan: &Channel) -> Result<IterMut<T>> {
+        Self::check_sample_size::<T>(chan)?;
+        let (ptr, len, step) = self.channel_span(chan);
+        check_aligned::<T>(ptr, step as usize)?;
+
+        Ok(IterMut {
+            phantom: PhantomData,
+            ptr,
+            len,
+            step,
+        })
+    }
+
+    /// Fills the buffer with samples for a channel from an iterator.
+    ///
+    /// This writes values from the iterator into the buffer, in the
+    /// position for the channel, until either the iterator or the buffer
+    /// is exhausted. It returns the number of samples written.
+    ///
+    /// The values are written as-is, so they should already be in the
+    /// hardware format. See [`Channel::convert_inverse()`].
+    pub fn fill_from<T, I>(&mut self, chan: &Channel, iter: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut n = 0;
+        for (samp, val) in self.channel_iter_mut::<T>(chan)?.zip(iter) {
+            *samp = val;
+            n += 1;
+        }
+        Ok(n)
+    }
 }
 
-impl<'a> Iterator for AttrIterator<'a> {
-    type Item = String;
+/// Operations that are only valid for input buffers.
+impl<D: InputDirection> Buffer<D> {
+    /// Fetch more samples from the hardware.
+    ///
+    /// This is only valid for input buffers.
+    pub fn refill(&mut self) -> Result<usize> {
+        let n = self.timed(|| {
+            let ret = unsafe { ffi::iio_buffer_refill(self.buf) };
+            sys_result(ret as i32, ret as usize)
+        })?;
+        self.check_overrun();
+        Ok(n)
+    }
 
-    /// Gets the next Buffer attribute from the iterator
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.buf.get_attr(self.idx) {
-            Ok(name) => {
-                self.idx += 1;
-                Some(name)
+    /// Fetch more samples from the hardware, with a timeout.
+    ///
+    /// This temporarily overrides the I/O timeout of the context for this
+    /// single operation. The previous timeout is restored afterward. A
+    /// timeout of zero specifies that no timeout should be used.
+    ///
+    /// The override applies to the whole context, so any I/O on the same
+    /// context from other threads uses it too while this is in progress.
+    /// Other timeout changes wait until it completes.
+    ///
+    /// This is only valid for input buffers.
+    pub fn refill_timeout(&mut self, timeout: Duration) -> Result<usize> {
+        self.dev.context().with_timeout(timeout, || self.refill())
+    }
+
+    /// Try to fetch more samples from the hardware, without blocking.
+    ///
+    /// This is intended for use with a buffer that was put into
+    /// non-blocking mode with [`Buffer::set_blocking_mode()`]. If no data
+    /// is available yet, this returns `Ok(None)`, rather than an error,
+    /// otherwise it returns the number of bytes read.
+    ///
+    /// This is only valid for input buffers.
+    pub fn try_refill(&mut self) -> Result<Option<usize>> {
+        let n = self.timed(|| would_block_result(unsafe { ffi::iio_buffer_refill(self.buf) }))?;
+        if n.is_some() {
+            self.check_overrun();
+        }
+        Ok(n)
+    }
+
+    /// Enables or disables overrun detection for the buffer.
+    ///
+    /// When enabled, the buffer checks the state of the kernel FIFO after
+    /// each refill, using the `data_available` and `length` buffer
+    /// attributes of the device. If the FIFO is full, the hardware is
+    /// producing samples faster than they are being read, and new samples
+    /// are being dropped. This is counted as an overrun.
+    ///
+    /// The `length` is in sample sets, but DMA buffers, which have a
+    /// `length_align_bytes` attribute, report `data_available` in bytes,
+    /// so it's converted to sample sets for those.
+    ///
+    /// This is off by default, since it reads two attributes on each
+    /// refill, which can be slow, particularly for network contexts. It
+    /// has no effect if the device doesn't have the attributes.
+    pub fn set_overrun_detection(&mut self, on: bool) {
+        self.detect_overruns = on;
+    }
+
+    /// Gets the number of overruns detected since the buffer was created.
+    ///
+    /// This is always zero unless overrun detection was enabled with
+    /// [`Buffer::set_overrun_detection()`].
+    pub fn overrun_count(&self) -> u64 {
+        self.overruns
+    }
+
+    /// Checks the kernel FIFO for an overrun, if detection is enabled.
+    fn check_overrun(&mut self) {
+        if self.detect_overruns {
+            let avail = self.attr_read_int("data_available");
+            let len = self.attr_read_int("length");
+            if let (Ok(avail), Ok(len)) = (avail, len) {
+                let sample_size = if self.has_attr("length_align_bytes") {
+                    match self.sample_size() {
+                        Ok(n) => Some(n),
+                        Err(_) => return,
+                    }
+                }
+                else {
+                    None
+                };
+                if is_overrun(avail, len, sample_size) {
+                    self.overruns += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Operations that are only valid for output buffers.
+impl<D: OutputDirection> Buffer<D> {
+    /// Send the samples to the hardware.
+    ///
+    /// This is only valid for output buffers.
+    pub fn push(&self) -> Result<usize> {
+        self.timed(|| {
+            let ret = unsafe { ffi::iio_buffer_push(self.buf) };
+            sys_result(ret as i32, ret as usize)
+        })
+    }
+
+    /// Send a given number of samples to the hardware.
+    ///
+    /// This is only valid for output buffers. Note that the number of samples
+    /// explicitly doesn't refer to their size in bytes, but the actual number
+    /// of samples, regardless of the sample size in memory.
+    pub fn push_partial(&self, num_samples: usize) -> Result<usize> {
+        self.timed(|| {
+            let ret = unsafe { ffi::iio_buffer_push_partial(self.buf, num_samples) };
+            sys_result(ret as i32, ret as usize)
+        })
+    }
+
+    /// Send the samples to the hardware, with a timeout.
+    ///
+    /// This temporarily overrides the I/O timeout of the context for this
+    /// single operation. The previous timeout is restored afterward. A
+    /// timeout of zero specifies that no timeout should be used.
+    ///
+    /// The override applies to the whole context, so any I/O on the same
+    /// context from other threads uses it too while this is in progress.
+    /// Other timeout changes wait until it completes.
+    ///
+    /// This is only valid for output buffers.
+    pub fn push_timeout(&self, timeout: Duration) -> Result<usize> {
+        self.dev.context().with_timeout(timeout, || self.push())
+    }
+
+    /// Try to send the samples to the hardware, without blocking.
+    ///
+    /// This is intended for use with a buffer that was put into
+    /// non-blocking mode with [`Buffer::set_blocking_mode()`]. If the
+    /// hardware can't accept the data yet, this returns `Ok(None)`, rather
+    /// than an error, otherwise it returns the number of bytes written.
+    ///
+    /// This is only valid for output buffers.
+    pub fn try_push(&self) -> Result<Option<usize>> {
+        self.timed(|| would_block_result(unsafe { ffi::iio_buffer_push(self.buf) }))
+    }
+
+    /// Waits for the hardware to consume all the samples that were pushed.
+    ///
+    /// A push only queues the samples in the kernel. An application that
+    /// exits right after pushing a short waveform can have the output cut
+    /// off when the buffer is destroyed. This polls the `data_available`
+    /// buffer attribute of the device until the kernel queue is empty, or
+    /// the timeout expires, in which case an `ETIMEDOUT` error is returned.
+    ///
+    /// For an output buffer, `data_available` reports the free space in
+    /// the queue, not the queued data, so the queue is empty when it
+    /// returns to the value it had when the buffer was created, before
+    /// the first push. A buffer made by [`Buffer::from_raw()`] records the
+    /// value when it's wrapped, so its queue should be empty at that time.
+    ///
+    /// The last block of samples might still be in flight to the hardware
+    /// when this returns. A cyclic buffer never drains, so this returns an
+    /// error for one, as it does if the device has no `data_available`
+    /// attribute.
+    pub fn drain(&self, timeout: Duration) -> Result<()> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+        if self.cyclic {
+            return Err(Error::General("Can't drain a cyclic buffer".into()));
+        }
+
+        let empty = self.empty_space.ok_or_else(|| {
+            Error::General("Can't determine the free space of the output queue".into())
+        })?;
+
+        let start = Instant::now();
+        while !is_drained(self.attr_read_int("data_available")?, empty) {
+            if start.elapsed() >= timeout {
+                return Err(Errno::ETIMEDOUT.into());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+}
+
+// Determines if an input FIFO of `len` sample sets is full, from the data
+// available in it. This is in bytes if the `sample_size` is given, as for
+// DMA buffers, otherwise in sample sets.
+fn is_overrun(avail: i64, len: i64, sample_size: Option<usize>) -> bool {
+    let avail = sample_size.map_or(avail, |n| avail / n.max(1) as i64);
+    len > 0 && avail >= len
+}
+
+// Reads the free space in the kernel queue of an output device, from its
+// `data_available` buffer attribute, if it has one. This is `None` for an
+// input device, to avoid the extra read when creating an input buffer.
+fn output_queue_space(dev: &Device) -> Option<i64> {
+    let is_output = dev.enabled_channels().iter().any(|chan| chan.is_output());
+    if is_output && dev.has_buffer_attr("data_available") {
+        dev.buffer_attr_read_int("data_available").ok()
+    }
+    else {
+        None
+    }
+}
+
+// Determines if an output queue has drained, from its current free space,
+// `avail`, and the free space when it was empty.
+fn is_drained(avail: i64, empty: i64) -> bool {
+    avail >= empty
+}
+
+// Converts the return value from a non-blocking buffer operation into a
+// Result, where an EAGAIN error means that the operation would block.
+fn would_block_result(ret: isize) -> Result<Option<usize>> {
+    if ret == -(Errno::EAGAIN as isize) {
+        Ok(None)
+    }
+    else {
+        sys_result(ret as i32, Some(ret as usize))
+    }
+}
+
+// Checks that the samples of a channel, starting at `ptr` and spaced
+// `step` bytes apart, can be referenced as values of type `T`.
+fn check_aligned<T>(ptr: *const u8, step: usize) -> Result<()> {
+    let align = mem::align_of::<T>();
+    if ptr as usize % align != 0 || step % align != 0 {
+        return Err(Error::WrongDataType);
+    }
+    Ok(())
+}
+
+// Callback from the C lib for each sample in the buffer.
+// See for_each_sample().
+unsafe extern "C" fn foreach_sample_cb<F>(
+    chan: *const ffi::iio_channel,
+    src: *mut c_void,
+    bytes: usize,
+    pdata: *mut c_void,
+) -> isize
+where
+    F: FnMut(&Channel, &[u8]),
+{
+    if chan.is_null() || src.is_null() || pdata.is_null() {
+        return -1;
+    }
+
+    let (f, ctx): &mut (F, Context) = &mut *pdata.cast();
+    let chan = Channel {
+        chan: chan as *mut ffi::iio_channel,
+        ctx: ctx.clone(),
+    };
+    f(&chan, slice::from_raw_parts(src as *const u8, bytes));
+    bytes as isize
+}
+
+/// Statistics for the transfers to or from a buffer.
+///
+/// These can help diagnose whether an application is limited by the I/O,
+/// such as the speed of a network connection, or by its own processing.
+/// If the buffer spends nearly all of the elapsed time in transfers, it is
+/// I/O bound. This is obtained from [`Buffer::stats()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BufferStats {
+    /// The number of successful refill or push operations
+    pub transfers: u64,
+    /// The total number of bytes transferred
+    pub bytes: u64,
+    /// The total number of sample sets transferred
+    pub samples: u64,
+    /// The total time spent in refill or push operations
+    pub busy_time: Duration,
+    /// The time taken by the most recent operation
+    pub last_time: Duration,
+    /// The longest time taken by any single operation
+    pub max_time: Duration,
+    /// The time since the statistics were started
+    pub elapsed: Duration,
+}
+
+impl BufferStats {
+    /// Records a single transfer of `n` bytes.
+    fn record(&mut self, n: usize, step: usize, dur: Duration) {
+        self.transfers += 1;
+        self.bytes += n as u64;
+        self.samples += n.checked_div(step).unwrap_or(0) as u64;
+        self.busy_time += dur;
+        self.last_time = dur;
+        self.max_time = self.max_time.max(dur);
+    }
+
+    /// Gets the average time taken by a transfer.
+    pub fn avg_time(&self) -> Duration {
+        match self.transfers {
+            0 => Duration::from_secs(0),
+            n => Duration::from_secs_f64(self.busy_time.as_secs_f64() / n as f64),
+        }
+    }
+
+    /// Gets the effective sample rate, in sample sets per second, over
+    /// the elapsed time.
+    pub fn sample_rate(&self) -> f64 {
+        Self::rate(self.samples, self.elapsed)
+    }
+
+    /// Gets the effective data rate, in bytes per second, over the
+    /// elapsed time.
+    pub fn byte_rate(&self) -> f64 {
+        Self::rate(self.bytes, self.elapsed)
+    }
+
+    /// Gets the fraction of the elapsed time that was spent in transfers,
+    /// in the range 0.0 to 1.0.
+    pub fn busy_fraction(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed > 0.0 {
+            (self.busy_time.as_secs_f64() / elapsed).min(1.0)
+        }
+        else {
+            0.0
+        }
+    }
+
+    // Gets the rate of the count over the duration.
+    fn rate(count: u64, dur: Duration) -> f64 {
+        let secs = dur.as_secs_f64();
+        if secs > 0.0 {
+            count as f64 / secs
+        }
+        else {
+            0.0
+        }
+    }
+}
+
+/// A builder to configure and create a [`Buffer`] for a device.
+///
+/// This is obtained from [`Device::buffer_builder()`]. At a minimum, the
+/// number of samples for the buffer should be set before it is created.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// let ctx = iio::Context::new().unwrap();
+/// let dev = ctx.find_device("dac0").unwrap();
+///
+/// let buf = dev.buffer_builder()
+///     .sample_count(1024)
+///     .cyclic(true)
+///     .num_kernel_buffers(4)
+///     .create()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct BufferBuilder {
+    /// The device for which the buffer will be created
+    dev: Device,
+    /// The number of samples the buffer should hold
+    sample_count: usize,
+    /// Whether to create the buffer in cyclic mode
+    cyclic: bool,
+    /// The buffer watermark, if it should be set
+    watermark: Option<usize>,
+    /// The number of kernel buffers, if it should be set
+    num_kernel_buffers: Option<u32>,
+}
+
+impl BufferBuilder {
+    /// Creates a new buffer builder for the device.
+    pub(crate) fn new(dev: &Device) -> Self {
+        Self {
+            dev: dev.clone(),
+            sample_count: 0,
+            cyclic: false,
+            watermark: None,
+            num_kernel_buffers: None,
+        }
+    }
+
+    /// Sets the number of samples the buffer should hold.
+    pub fn sample_count(mut self, n: usize) -> Self {
+        self.sample_count = n;
+        self
+    }
+
+    /// Sets whether the buffer should be created in cyclic mode.
+    ///
+    /// See [`Device::create_buffer()`] for details about cyclic buffers.
+    pub fn cyclic(mut self, cyclic: bool) -> Self {
+        self.cyclic = cyclic;
+        self
+    }
+
+    /// Sets the watermark for the buffer.
+    ///
+    /// This is the number of samples the kernel waits for before it marks
+    /// the buffer as readable for `poll()`. It doesn't shorten a refill,
+    /// which still waits for the full sample count. It is written to the
+    /// `watermark` buffer attribute of the device before the buffer is
+    /// created. See [`Device::set_buffer_watermark()`].
+    pub fn watermark(mut self, n: usize) -> Self {
+        self.watermark = Some(n);
+        self
+    }
+
+    /// Sets the number of kernel buffers for the device.
+    pub fn num_kernel_buffers(mut self, n: u32) -> Self {
+        self.num_kernel_buffers = Some(n);
+        self
+    }
+
+    /// Creates the buffer with the configured options.
+    pub fn create(self) -> Result<Buffer> {
+        self.create_typed()
+    }
+
+    /// Creates an input buffer with the configured options.
+    pub fn create_input(self) -> Result<InputBuffer> {
+        self.create_typed()
+    }
+
+    /// Creates an output buffer with the configured options.
+    pub fn create_output(self) -> Result<OutputBuffer> {
+        self.create_typed()
+    }
+
+    /// Creates a pool of `depth` input buffers, with the configured
+    /// options, that are refilled on a worker thread.
+    ///
+    /// See [`BufferPool`] for details.
+    pub fn create_pool(self, depth: usize) -> Result<BufferPool> {
+        BufferPool::new(self, depth)
+    }
+
+    /// Creates a buffer, of the requested direction, with the configured
+    /// options.
+    fn create_typed<D>(self) -> Result<Buffer<D>> {
+        if let Some(n) = self.num_kernel_buffers {
+            self.dev.set_num_kernel_buffers(n)?;
+        }
+
+        if let Some(n) = self.watermark {
+            self.dev.set_buffer_watermark(n)?;
+        }
+
+        self.dev.create_buffer_typed(self.sample_count, self.cyclic)
+    }
+}
+
+/// Destroy the underlying buffer when the object scope ends.
+impl<D> Drop for Buffer<D> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap_or_else(|err| err.into_inner());
+        shared.0 = ptr::null_mut();
+        if !self.buf.is_null() {
+            unsafe { ffi::iio_buffer_destroy(self.buf) }
+        }
+    }
+}
+
+/// A token to cancel the operations on a buffer from another thread.
+///
+/// This is obtained from [`Buffer::cancel_token()`]. It is cheap to clone,
+/// and can be sent to, and shared with, other threads.
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    /// Shared pointer to the C buffer
+    shared: Arc<Mutex<SharedBuf>>,
+}
+
+impl CancelToken {
+    /// Cancel all operations on the buffer.
+    ///
+    /// See [`Buffer::cancel()`]. This has no effect if the buffer has
+    /// already been dropped.
+    pub fn cancel(&self) {
+        let shared = self.shared.lock().unwrap_or_else(|err| err.into_inner());
+        if !shared.0.is_null() {
+            unsafe { ffi::iio_buffer_cancel(shared.0) };
+        }
+    }
+
+    /// Determines if the buffer for this token still exists.
+    pub fn is_alive(&self) -> bool {
+        let shared = self.shared.lock().unwrap_or_else(|err| err.into_inner());
+        !shared.0.is_null()
+    }
+
+    /// Gets a guard that will cancel the buffer operations when it is
+    /// dropped.
+    ///
+    /// This can be used to make sure that a blocked capture thread is
+    /// released when the scope that controls it exits, including by an
+    /// early return or panic.
+    pub fn guard(&self) -> CancelGuard {
+        CancelGuard {
+            token: self.clone(),
+        }
+    }
+}
+
+/// A guard that cancels the operations on a buffer when it is dropped.
+///
+/// This is obtained from [`CancelToken::guard()`].
+#[derive(Debug)]
+pub struct CancelGuard {
+    /// The token used to cancel the buffer
+    token: CancelToken,
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+/// An iterator that moves channel data out of a buffer.
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    phantom: PhantomData<T>,
+    // Pointer to the current sample for a channel
+    ptr: *const u8,
+    // The number of samples remaining
+    len: usize,
+    // The offset to the next sample for the channel, in bytes
+    step: isize,
+    // The size of the elements to byte-swap in each sample, if any
+    swap_len: usize,
+}
+
+impl<T> IntoIter<T> {
+    // Reads the sample from the pointer, swapping the bytes if needed.
+    unsafe fn read(&self, p: *const u8) -> T {
+        let mut val = ptr::read_unaligned(p.cast::<T>());
+        if self.swap_len > 1 {
+            let pval = (&mut val as *mut T).cast::<u8>();
+            let bytes = slice::from_raw_parts_mut(pval, mem::size_of::<T>());
+            bytes.chunks_mut(self.swap_len).for_each(|elem| elem.reverse());
+        }
+        val
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let prev = self.ptr;
+            self.ptr = self.ptr.wrapping_offset(self.step);
+            self.len -= 1;
+            Some(unsafe { self.read(prev) })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            self.len -= 1;
+            let prev = self.ptr.wrapping_offset(self.len as isize * self.step);
+            Some(unsafe { self.read(prev) })
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+/// A single sample set from a buffer.
+///
+/// A frame holds one sample from each of the channels that were enabled
+/// when the buffer was created, all taken at the same index in the buffer.
+/// 'a Lifetime of the Buffer
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a, D = AnyDirection> {
+    /// The buffer containing the frame
+    buf: &'a Buffer<D>,
+    /// Pointer to the start of the frame in the buffer
+    ptr: *const u8,
+    /// The size of the frame, in bytes
+    len: usize,
+}
+
+impl<'a, D> Frame<'a, D> {
+    /// Gets the sample for a channel from the frame.
+    ///
+    /// The size of the sample type, `T`, must match the size of the samples
+    /// for the channel, and the channel must be enabled in the buffer.
+    pub fn get<T: Copy>(&self, chan: &Channel) -> Result<T> {
+        Buffer::<D>::check_sample_size::<T>(chan)?;
+        let offset = self.buf.channel_offset(chan);
+        if offset + mem::size_of::<T>() > self.len {
+            return Err(Error::ChannelNotEnabled);
+        }
+        Ok(unsafe { ptr::read_unaligned(self.ptr.add(offset).cast()) })
+    }
+
+    /// Gets the raw bytes of the frame, in the hardware format.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+/// An iterator over the sample sets (frames) in a buffer.
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct FrameIter<'a, D = AnyDirection> {
+    /// The buffer containing the frames
+    buf: &'a Buffer<D>,
+    // Pointer to the start of the current frame
+    ptr: *const u8,
+    // The number of frames remaining
+    len: usize,
+    // The size of each frame, in bytes
+    step: usize,
+}
+
+impl<'a, D> FrameIter<'a, D> {
+    // Gets the frame at the specified index from the current one.
+    fn frame(&self, idx: usize) -> Frame<'a, D> {
+        Frame {
+            buf: self.buf,
+            ptr: self.ptr.wrapping_add(idx * self.step),
+            len: self.step,
+        }
+    }
+}
+
+impl<'a, D> Iterator for FrameIter<'a, D> {
+    type Item = Frame<'a, D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let frame = self.frame(0);
+            self.ptr = self.ptr.wrapping_add(self.step);
+            self.len -= 1;
+            Some(frame)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, D> DoubleEndedIterator for FrameIter<'a, D> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            self.len -= 1;
+            Some(self.frame(self.len))
+        }
+    }
+}
+
+impl<'a, D> ExactSizeIterator for FrameIter<'a, D> {}
+
+/// A view of a number of consecutive sample sets (frames) from a buffer.
+/// 'a Lifetime of the Buffer
+#[derive(Debug, Clone, Copy)]
+pub struct Chunk<'a, D = AnyDirection> {
+    /// The buffer containing the chunk
+    buf: &'a Buffer<D>,
+    /// Pointer to the start of the chunk in the buffer
+    ptr: *const u8,
+    /// The number of frames in the chunk
+    len: usize,
+    /// The size of each frame, in bytes
+    step: usize,
+}
+
+impl<'a, D> Chunk<'a, D> {
+    /// Gets the number of sample sets (frames) in the chunk.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Determines if the chunk is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gets an iterator over the frames in the chunk.
+    pub fn frames(&self) -> FrameIter<'a, D> {
+        FrameIter {
+            buf: self.buf,
+            ptr: self.ptr,
+            len: self.len,
+            step: self.step,
+        }
+    }
+
+    /// Gets an iterator over references to the data from a channel in
+    /// the chunk.
+    ///
+    /// The size of the sample type, `T`, must match the size of the samples
+    /// for the channel, and the channel must be enabled in the buffer. For
+    /// a channel with repeated elements, `T` can be an array, like
+    /// `[i16; 3]`.
+    pub fn channel_iter<T>(&self, chan: &Channel) -> Result<Iter<'a, T>> {
+        Buffer::<D>::check_sample_size::<T>(chan)?;
+        let offset = self.buf.channel_offset(chan);
+        if offset + mem::size_of::<T>() > self.step {
+            return Err(Error::ChannelNotEnabled);
+        }
+
+        let ptr = self.ptr.wrapping_add(offset);
+        check_aligned::<T>(ptr, self.step)?;
+
+        Ok(Iter {
+            phantom: PhantomData,
+            ptr,
+            len: self.len,
+            step: self.step as isize,
+        })
+    }
+
+    /// Gets the raw bytes of the chunk, in the hardware format.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len * self.step) }
+    }
+}
+
+/// An iterator over chunks of sample sets in a buffer.
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct Chunks<'a, D = AnyDirection> {
+    /// The buffer containing the chunks
+    buf: &'a Buffer<D>,
+    // Pointer to the start of the current chunk
+    ptr: *const u8,
+    // The number of frames remaining
+    len: usize,
+    // The size of each frame, in bytes
+    step: usize,
+    // The number of frames in each chunk
+    chunk_len: usize,
+}
+
+impl<'a, D> Iterator for Chunks<'a, D> {
+    type Item = Chunk<'a, D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let n = self.len.min(self.chunk_len);
+            let chunk = Chunk {
+                buf: self.buf,
+                ptr: self.ptr,
+                len: n,
+                step: self.step,
+            };
+            self.ptr = self.ptr.wrapping_add(n * self.step);
+            self.len -= n;
+            Some(chunk)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = (self.len + self.chunk_len - 1) / self.chunk_len;
+        (n, Some(n))
+    }
+}
+
+impl<'a, D> ExactSizeIterator for Chunks<'a, D> {}
+
+/// An iterator over the scaled values of the samples from a channel.
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct ScaledIter<'a> {
+    phantom: PhantomData<&'a u8>,
+    // The data format of the channel
+    fmt: DataFormat,
+    // Pointer to the current sample for a channel
+    ptr: *const u8,
+    // The number of samples remaining
+    len: usize,
+    // The offset to the next sample for the channel, in bytes
+    step: usize,
+    // The scale to apply to each sample
+    scale: f64,
+    // The offset to apply to each sample
+    offset: f64,
+}
+
+impl<'a> ScaledIter<'a> {
+    // Converts the sample at the specified index from the current one.
+    fn value(&self, idx: usize) -> f64 {
+        let nbytes = (self.fmt.length() / 8) as usize;
+        let raw = unsafe { slice::from_raw_parts(self.ptr.wrapping_add(idx * self.step), nbytes) };
+        let val = self.fmt.decode_f64(raw).unwrap_or(f64::NAN);
+        (val + self.offset) * self.scale
+    }
+}
+
+impl<'a> Iterator for ScaledIter<'a> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let val = self.value(0);
+            self.ptr = self.ptr.wrapping_add(self.step);
+            self.len -= 1;
+            Some(val)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for ScaledIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            self.len -= 1;
+            Some(self.value(self.len))
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for ScaledIter<'a> {}
+
+/// An iterator over dynamically-typed samples from a channel.
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct DynIter<'a> {
+    phantom: PhantomData<&'a u8>,
+    // The data format of the channel
+    fmt: DataFormat,
+    // Pointer to the current sample for a channel
+    ptr: *const u8,
+    // The number of samples remaining
+    len: usize,
+    // The offset to the next sample for the channel, in bytes
+    step: usize,
+}
+
+impl<'a> DynIter<'a> {
+    // Decodes the sample at the specified index from the current one.
+    fn value(&self, idx: usize) -> Option<SampleValue> {
+        let nbytes = (self.fmt.length() / 8) as usize;
+        let raw = unsafe { slice::from_raw_parts(self.ptr.wrapping_add(idx * self.step), nbytes) };
+        self.fmt.decode_value(raw)
+    }
+}
+
+impl<'a> Iterator for DynIter<'a> {
+    type Item = SampleValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let val = self.value(0);
+            self.ptr = self.ptr.wrapping_add(self.step);
+            self.len -= 1;
+            val
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a> ExactSizeIterator for DynIter<'a> {}
+
+/// An iterator that borrows channel data from a buffer.
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    phantom: PhantomData<&'a T>,
+    // Pointer to the current sample for a channel
+    ptr: *const u8,
+    // The number of samples remaining
+    len: usize,
+    // The offset to the next sample for the channel, in bytes
+    step: isize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let prev = self.ptr;
+            self.ptr = self.ptr.wrapping_offset(self.step);
+            self.len -= 1;
+            Some(unsafe { &*prev.cast::<T>() })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            self.len -= 1;
+            let prev = self.ptr.wrapping_offset(self.len as isize * self.step);
+            Some(unsafe { &*prev.cast::<T>() })
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+/// An iterator over the repeated elements of channel samples in a buffer.
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct RepeatIter<'a, T> {
+    phantom: PhantomData<&'a T>,
+    // Pointer to the current sample for a channel
+    ptr: *const u8,
+    // The number of samples remaining
+    len: usize,
+    // The offset to the next sample for the channel, in bytes
+    step: isize,
+    // The number of elements in each sample
+    repeat: usize,
+}
+
+impl<'a, T> Iterator for RepeatIter<'a, T> {
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let prev = self.ptr;
+            self.ptr = self.ptr.wrapping_offset(self.step);
+            self.len -= 1;
+            Some(unsafe { slice::from_raw_parts(prev.cast::<T>(), self.repeat) })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for RepeatIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            self.len -= 1;
+            let prev = self.ptr.wrapping_offset(self.len as isize * self.step);
+            Some(unsafe { slice::from_raw_parts(prev.cast::<T>(), self.repeat) })
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for RepeatIter<'a, T> {}
+
+/// An iterator that gives mutable access to channel data in a buffer.
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct IterMut<'a, T> {
+    phantom: PhantomData<&'a mut T>,
+    // Pointer to the current sample for a channel
+    ptr: *mut u8,
+    // The number of samples remaining
+    len: usize,
+    // The offset to the next sample for the channel, in bytes
+    step: isize,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            let prev = self.ptr;
+            self.ptr = self.ptr.wrapping_offset(self.step);
+            self.len -= 1;
+            Some(unsafe { &mut *prev.cast::<T>() })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            None
+        }
+        else {
+            self.len -= 1;
+            let prev = self.ptr.wrapping_offset(self.len as isize * self.step);
+            Some(unsafe { &mut *prev.cast::<T>() })
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+/// Iterator over the buffer attributes
+/// 'a Lifetime of the Buffer
+#[derive(Debug)]
+pub struct AttrIterator<'a, D = AnyDirection> {
+    /// Reference to the Buffer that we're scanning for attributes
+    buf: &'a Buffer<D>,
+    /// Index to the next Buffer attribute from the iterator
+    idx: usize,
+}
+
+impl<'a, D> Iterator for AttrIterator<'a, D> {
+    type Item = String;
+
+    /// Gets the next Buffer attribute from the iterator
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.buf.get_attr(self.idx) {
+            Ok(name) => {
+                self.idx += 1;
+                Some(name)
             }
             Err(_) => None,
         }
     }
 }
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Make sure EAGAIN is reported as "would block", not an error.
+    #[test]
+    fn would_block() {
+        assert_eq!(would_block_result(128).unwrap(), Some(128));
+        assert_eq!(would_block_result(-(Errno::EAGAIN as isize)).unwrap(), None);
+        assert!(would_block_result(-(Errno::EIO as isize)).is_err());
+    }
+
+    // The FIFO is full when the data available reaches its length, in
+    // sample sets, even when the data available is given in bytes.
+    #[test]
+    fn overrun_check() {
+        assert!(is_overrun(1024, 1024, None));
+        assert!(!is_overrun(1023, 1024, None));
+        assert!(!is_overrun(0, 0, None));
+
+        // A DMA buffer of 1024 sample sets of 4 bytes each
+        assert!(!is_overrun(1024, 1024, Some(4)));
+        assert!(!is_overrun(4095, 1024, Some(4)));
+        assert!(is_overrun(4096, 1024, Some(4)));
+    }
+
+    // An output queue is drained when its free space is back to the
+    // amount it had when empty, not when it reads zero.
+    #[test]
+    fn output_queue_drained() {
+        assert!(is_drained(4096, 4096));
+        assert!(!is_drained(4095, 4096));
+        assert!(!is_drained(0, 4096));
+    }
+
+    // A token for a destroyed buffer should be harmless.
+    #[test]
+    fn cancel_token_after_drop() {
+        let token = CancelToken {
+            shared: Arc::new(Mutex::new(SharedBuf(ptr::null_mut()))),
+        };
+        assert!(!token.is_alive());
+        token.cancel();
+        drop(token.guard());
+    }
+
+    // Transfer statistics accumulate and give the effective rates.
+    #[test]
+    fn buffer_stats() {
+        let mut stats = BufferStats::default();
+        assert_eq!(stats.sample_rate(), 0.0);
+
+        stats.record(400, 4, Duration::from_millis(100));
+        stats.record(400, 4, Duration::from_millis(300));
+        stats.elapsed = Duration::from_secs(1);
+
+        assert_eq!(stats.transfers, 2);
+        assert_eq!(stats.samples, 200);
+        assert_eq!(stats.max_time, Duration::from_millis(300));
+        assert_eq!(stats.avg_time(), Duration::from_millis(200));
+        assert_eq!(stats.sample_rate(), 200.0);
+        assert_eq!(stats.byte_rate(), 800.0);
+        assert!((stats.busy_fraction() - 0.4).abs() < 1e-9);
+    }
+
+    // A repeat channel followed by a timestamp is stepped by whole sample
+    // sets, even though the set isn't a multiple of the sample size.
+    #[test]
+    fn ref_iter_repeat_with_timestamp() {
+        // Two sets of [x, y, z, pad] as i16, then a 64-bit timestamp
+        let data: [i16; 16] = [1, 2, 3, 0, -1, -1, -1, -1, 4, 5, 6, 0, -1, -1, -1, -1];
+        let ptr = data.as_ptr().cast::<u8>();
+        assert!(check_aligned::<[i16; 3]>(ptr, 16).is_ok());
+        assert!(check_aligned::<u32>(ptr, 6).is_err());
+
+        let iter: Iter<[i16; 3]> = Iter {
+            phantom: PhantomData,
+            ptr,
+            len: 2,
+            step: 16,
+        };
+        assert_eq!(iter.copied().collect::<Vec<_>>(), vec![[1, 2, 3], [4, 5, 6]]);
+    }
+
+    // Copying and mutable iterators also step a repeat channel by the
+    // whole sample set, leaving the timestamp alone.
+    #[test]
+    fn channel_iter_repeat_with_timestamp() {
+        let mut data: [i16; 16] = [1, 2, 3, 0, -1, -1, -1, -1, 4, 5, 6, 0, -1, -1, -1, -1];
+
+        let iter: IntoIter<[i16; 3]> = IntoIter {
+            phantom: PhantomData,
+            ptr: data.as_ptr().cast(),
+            len: 2,
+            step: 16,
+            swap_len: 0,
+        };
+        assert_eq!(iter.rev().collect::<Vec<_>>(), vec![[4, 5, 6], [1, 2, 3]]);
+
+        let iter: IterMut<[i16; 3]> = IterMut {
+            phantom: PhantomData,
+            ptr: data.as_mut_ptr().cast(),
+            len: 2,
+            step: 16,
+        };
+        for samp in iter {
+            samp.iter_mut().for_each(|x| *x *= 10);
+        }
+        assert_eq!(data, [10, 20, 30, 0, -1, -1, -1, -1, 40, 50, 60, 0, -1, -1, -1, -1]);
+    }
+}
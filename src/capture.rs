@@ -0,0 +1,391 @@
+// industrial-io/src/capture.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Helpers to capture blocks of samples from input buffers.
+//!
+//! These loop over refills of an input buffer, copying the sample sets out
+//! of it into a [`Capture`], which holds the raw data, in the format used
+//! by the hardware, along with the layout of the enabled channels within
+//! each sample set.
+//!
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use super::*;
+use crate::pool::Layout;
+
+/// The largest buffer, in samples, that a device creates to make a capture.
+/// Longer captures are made with multiple refills of the buffer.
+const MAX_CAPTURE_BUFFER_SIZE: usize = 65536;
+
+/// A block of sample sets captured from an input buffer.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// The raw sample sets
+    data: Vec<u8>,
+    /// The layout of the data
+    layout: Layout,
+}
+
+impl Capture {
+    /// Creates an empty capture for the data in the buffer.
+    pub(crate) fn new<D>(buf: &Buffer<D>) -> Self {
+        Self {
+            data: Vec::new(),
+            layout: Layout::new(buf),
+        }
+    }
+
+    /// Appends up to `max` complete sample sets from the raw data,
+    /// returning the number that were added.
+    pub(crate) fn append(&mut self, raw: &[u8], max: usize) -> usize {
+        let n = self.layout.len(raw).min(max);
+        self.data.extend_from_slice(&raw[..n * self.layout.step]);
+        n
+    }
+
+    /// Gets the number of bytes from one sample set to the next.
+    pub fn step(&self) -> usize {
+        self.layout.step
+    }
+
+    /// Gets the number of sample sets in the capture.
+    pub fn len(&self) -> usize {
+        self.layout.len(&self.data)
+    }
+
+    /// Determines if the capture is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the raw contents of the capture as a slice of bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Gets an iterator for the data from a channel.
+    ///
+    /// The channel must have been enabled for the buffer that was used for
+    /// the capture, and the size of the sample type, `T`, must match the
+    /// size of the samples for the channel, otherwise an error is returned.
+    /// The samples are in the format used by the hardware.
+    pub fn channel_iter<T: Copy>(&self, chan: &Channel) -> Result<impl Iterator<Item = T> + '_> {
+        self.layout.channel_iter(&self.data, chan)
+    }
+}
+
+impl<D: InputDirection> Buffer<D> {
+    /// Captures exactly `n` sample sets from the buffer.
+    ///
+    /// This refills the buffer as many times as needed to accumulate the
+    /// requested number of sample sets, handling any short reads. Only the
+    /// part of the last refill that is needed is kept.
+    pub fn capture_exact(&mut self, n: usize) -> Result<Capture> {
+        let step = self.step();
+        if step == 0 {
+            return Err(Error::BadReturnSize);
+        }
+
+        let mut cap = Capture::new(self);
+        cap.data.reserve(n * step);
+
+        let mut remaining = n;
+        while remaining > 0 {
+            self.refill()?;
+            remaining -= cap.append(self.as_bytes(), remaining);
+        }
+        Ok(cap)
+    }
+
+    /// Captures the sample sets from the buffer for the specified amount
+    /// of time.
+    ///
+    /// If the device has a sampling frequency, as given by
+    /// [`Device::sampling_frequency()`], this is used to determine the
+    /// number of sample sets for the duration, which are then captured
+    /// with [`Buffer::capture_exact()`]. Otherwise, the buffer is refilled
+    /// until the wall-clock time has elapsed, and all the data from the
+    /// refills is returned.
+    pub fn capture_for(&mut self, dur: Duration) -> Result<Capture> {
+        match self.dev.sampling_frequency() {
+            Ok(Hertz(freq)) if freq > 0.0 => {
+                let n = (freq * dur.as_secs_f64()).round() as usize;
+                self.capture_exact(n)
+            }
+            _ => self.capture_for_wall_clock(dur),
+        }
+    }
+
+    /// Refills the buffer until the wall-clock time has elapsed.
+    fn capture_for_wall_clock(&mut self, dur: Duration) -> Result<Capture> {
+        if self.step() == 0 {
+            return Err(Error::BadReturnSize);
+        }
+
+        let mut cap = Capture::new(self);
+        let start = Instant::now();
+        while start.elapsed() < dur {
+            self.refill()?;
+            cap.append(self.as_bytes(), usize::MAX);
+        }
+        Ok(cap)
+    }
+}
+
+impl Device {
+    /// Captures exactly `n` sample sets from the device.
+    ///
+    /// This creates an input buffer for the enabled channels of the device,
+    /// and refills it until the requested number of sample sets has been
+    /// captured. The buffer is destroyed when the capture is complete.
+    pub fn capture_exact(&self, n: usize) -> Result<Capture> {
+        let nbuf = n.min(MAX_CAPTURE_BUFFER_SIZE).max(1);
+        self.create_input_buffer(nbuf)?.capture_exact(n)
+    }
+
+    /// Captures the sample sets from the device for the specified amount
+    /// of time.
+    ///
+    /// This creates an input buffer for the enabled channels of the device,
+    /// and captures from it with [`Buffer::capture_for()`]. The buffer is
+    /// destroyed when the capture is complete.
+    ///
+    /// `dur` The amount of time to capture.
+    /// `sample_count` The number of samples the buffer should hold
+    pub fn capture_for(&self, dur: Duration, sample_count: usize) -> Result<Capture> {
+        self.create_input_buffer(sample_count)?.capture_for(dur)
+    }
+
+    /// Reads a single sample from each of the specified channels.
+    ///
+    /// This enables the input channels with the IDs, creates a buffer for
+    /// a single sample set, refills it once, and returns the samples in
+    /// physical units (scale and offset applied), in the same order as the
+    /// IDs. The channels that were enabled before the call are restored
+    /// afterwards.
+    ///
+    /// This is a convenience for slow sensors, like those for temperature
+    /// or humidity, when only the current values are needed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use industrial_io as iio;
+    ///
+    /// let ctx = iio::Context::new().unwrap();
+    /// let dev = ctx.find_device("si7020").unwrap();
+    /// let vals = dev.read_sample_set(&["temp", "humidityrelative"]).unwrap();
+    /// println!("{} C, {} %RH", vals[0] / 1000.0, vals[1] / 1000.0);
+    /// ```
+    pub fn read_sample_set(&self, ids: &[&str]) -> Result<Vec<f64>> {
+        let prev = self.enabled_channels();
+        let res = self.read_sample_set_inner(ids);
+
+        self.disable_all();
+        for chan in prev {
+            let _ = chan.enable();
+        }
+        res
+    }
+
+    /// Enables the channels, and reads one sample set from them.
+    fn read_sample_set_inner(&self, ids: &[&str]) -> Result<Vec<f64>> {
+        let chans = ids
+            .iter()
+            .map(|id| {
+                self.find_channel(id, false)
+                    .ok_or_else(|| Error::General(format!("Channel not found: {}", id)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.disable_all();
+        for chan in &chans {
+            chan.enable()?;
+        }
+
+        let mut buf = self.create_input_buffer(1)?;
+        buf.refill()?;
+
+        chans
+            .iter()
+            .map(|chan| {
+                let conv = chan.converter()?;
+                let mut raw = vec![0u8; chan.data_format().byte_length()];
+                let n = chan.read_raw_bytes_into(&buf, &mut raw)?;
+                conv.decode(&raw[..n]).ok_or(Error::BadReturnSize)
+            })
+            .collect()
+    }
+}
+
+/// The condition on the value of a channel that fires a software trigger.
+///
+/// The level is compared against the samples of the channel after they are
+/// decoded from the hardware format, with the shift and sign applied, but
+/// before any scale or offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerCondition {
+    /// Fires on a sample at or above the level
+    Above(f64),
+    /// Fires on a sample at or below the level
+    Below(f64),
+    /// Fires when the samples cross the level going up
+    RisingEdge(f64),
+    /// Fires when the samples cross the level going down
+    FallingEdge(f64),
+}
+
+impl TriggerCondition {
+    /// Determines if the condition fires for the current sample, given
+    /// the previous one, if any.
+    fn fires(&self, prev: Option<f64>, cur: f64) -> bool {
+        use TriggerCondition::*;
+        match (*self, prev) {
+            (Above(level), _) => cur >= level,
+            (Below(level), _) => cur <= level,
+            (RisingEdge(level), Some(prev)) => prev < level && cur >= level,
+            (FallingEdge(level), Some(prev)) => prev > level && cur <= level,
+            _ => false,
+        }
+    }
+}
+
+/// An oscilloscope-style capture around a software trigger.
+///
+/// This continuously refills an input buffer, keeping a rolling history of
+/// the most recent sample sets, until the trigger condition fires on the
+/// samples of a channel. It then returns the requested number of sample
+/// sets from before the trigger, followed by the requested number from
+/// after it. The sample set that fired the trigger is the first of the
+/// post-trigger samples.
+#[derive(Debug, Clone)]
+pub struct TriggeredCapture {
+    /// The channel to watch for the trigger
+    chan: Channel,
+    /// The condition that fires the trigger
+    cond: TriggerCondition,
+    /// The number of sample sets to keep from before the trigger
+    pre: usize,
+    /// The number of sample sets to capture after the trigger
+    post: usize,
+}
+
+impl TriggeredCapture {
+    /// Creates a capture that is triggered by a condition on the channel.
+    ///
+    /// By default, there are no pre-trigger samples, and a single
+    /// post-trigger sample.
+    pub fn new(chan: &Channel, cond: TriggerCondition) -> Self {
+        Self {
+            chan: chan.clone(),
+            cond,
+            pre: 0,
+            post: 1,
+        }
+    }
+
+    /// Sets the number of sample sets to return from before the trigger.
+    pub fn pre_trigger(mut self, n: usize) -> Self {
+        self.pre = n;
+        self
+    }
+
+    /// Sets the number of sample sets to return from after the trigger.
+    pub fn post_trigger(mut self, n: usize) -> Self {
+        self.post = n;
+        self
+    }
+
+    /// Runs the capture on the buffer.
+    ///
+    /// This blocks until the trigger fires, and all the post-trigger
+    /// samples have been captured. It can be cancelled from another thread
+    /// with a [`CancelToken`] for the buffer. If the trigger fires before
+    /// the history is full, fewer pre-trigger samples are returned.
+    pub fn capture<D: InputDirection>(&self, buf: &mut Buffer<D>) -> Result<Capture> {
+        if !self.chan.is_enabled() {
+            return Err(Error::ChannelNotEnabled);
+        }
+
+        let fmt = self.chan.data_format();
+        let step = buf.step();
+        let off = buf.channel_offset(&self.chan);
+        if step == 0 || off + fmt.byte_length() > step {
+            return Err(Error::BadReturnSize);
+        }
+
+        let mut cap = Capture::new(buf);
+        let mut hist: VecDeque<u8> = VecDeque::with_capacity(self.pre * step);
+        let mut prev = None;
+        let mut remaining = None;
+
+        loop {
+            buf.refill()?;
+            for frame in buf.as_bytes().chunks_exact(step) {
+                if remaining.is_none() {
+                    let cur = fmt.decode_f64(&frame[off..]).ok_or(Error::WrongDataType)?;
+                    if !self.cond.fires(prev, cur) {
+                        prev = Some(cur);
+                        if self.pre > 0 {
+                            if hist.len() == self.pre * step {
+                                hist.drain(..step);
+                            }
+                            hist.extend(frame);
+                        }
+                        continue;
+                    }
+                    cap.data.extend(hist.drain(..));
+                    remaining = Some(self.post);
+                }
+
+                match remaining {
+                    Some(n) if n > 0 => {
+                        cap.data.extend_from_slice(frame);
+                        remaining = Some(n - 1);
+                    }
+                    _ => return Ok(cap),
+                }
+            }
+
+            if remaining == Some(0) {
+                return Ok(cap);
+            }
+        }
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Level conditions fire on any sample. Edges need a previous sample.
+    #[test]
+    fn trigger_condition() {
+        use TriggerCondition::*;
+
+        assert!(Above(10.0).fires(None, 10.0));
+        assert!(!Above(10.0).fires(None, 9.0));
+        assert!(Below(10.0).fires(Some(20.0), 5.0));
+
+        assert!(!RisingEdge(10.0).fires(None, 12.0));
+        assert!(RisingEdge(10.0).fires(Some(8.0), 12.0));
+        assert!(!RisingEdge(10.0).fires(Some(11.0), 12.0));
+
+        assert!(FallingEdge(0.0).fires(Some(1.0), -1.0));
+        assert!(!FallingEdge(0.0).fires(Some(-1.0), -2.0));
+    }
+}
@@ -16,14 +16,20 @@ use std::{
     any::TypeId,
     collections::HashMap,
     ffi::CString,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
     mem,
     os::raw::{c_char, c_int, c_longlong, c_uint, c_void},
 };
 
 /// The type of data associated with a channel.
+///
+/// The `Display` and `FromStr` implementations use the names from the
+/// kernel ABI, as they appear in the sysfs attribute names, like "voltage"
+/// or "anglvel".
 #[allow(missing_docs)]
 #[repr(u32)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChannelType {
     Voltage = ffi::iio_chan_type_IIO_VOLTAGE,
     Current = ffi::iio_chan_type_IIO_CURRENT,
@@ -31,7 +37,7 @@ pub enum ChannelType {
     Accel = ffi::iio_chan_type_IIO_ACCEL,
     AnglVel = ffi::iio_chan_type_IIO_ANGL_VEL,
     Magn = ffi::iio_chan_type_IIO_MAGN,
-    Ligtht = ffi::iio_chan_type_IIO_LIGHT,
+    Light = ffi::iio_chan_type_IIO_LIGHT,
     Intensity = ffi::iio_chan_type_IIO_INTENSITY,
     Proximity = ffi::iio_chan_type_IIO_PROXIMITY,
     Temp = ffi::iio_chan_type_IIO_TEMP,
@@ -57,9 +63,289 @@ pub enum ChannelType {
     Count = ffi::iio_chan_type_IIO_COUNT,
     Index = ffi::iio_chan_type_IIO_INDEX,
     Gravity = ffi::iio_chan_type_IIO_GRAVITY,
+    PositionRelative = ffi::iio_chan_type_IIO_POSITIONRELATIVE,
+    Phase = ffi::iio_chan_type_IIO_PHASE,
+    MassConcentration = ffi::iio_chan_type_IIO_MASSCONCENTRATION,
     Unknown = ffi::iio_chan_type_IIO_CHAN_TYPE_UNKNOWN,
 }
 
+impl ChannelType {
+    /// The old, misspelled, name for the [`ChannelType::Light`] variant.
+    #[allow(non_upper_case_globals)]
+    #[deprecated(note = "Use ChannelType::Light")]
+    pub const Ligtht: ChannelType = ChannelType::Light;
+
+    /// The known channel types and their kernel ABI names.
+    const NAMES: &'static [(ChannelType, &'static str)] = &[
+        (ChannelType::Voltage, "voltage"),
+        (ChannelType::Current, "current"),
+        (ChannelType::Power, "power"),
+        (ChannelType::Accel, "accel"),
+        (ChannelType::AnglVel, "anglvel"),
+        (ChannelType::Magn, "magn"),
+        (ChannelType::Light, "illuminance"),
+        (ChannelType::Intensity, "intensity"),
+        (ChannelType::Proximity, "proximity"),
+        (ChannelType::Temp, "temp"),
+        (ChannelType::Incli, "incli"),
+        (ChannelType::Rot, "rot"),
+        (ChannelType::Angl, "angl"),
+        (ChannelType::Timestamp, "timestamp"),
+        (ChannelType::Capacitance, "capacitance"),
+        (ChannelType::AltVoltage, "altvoltage"),
+        (ChannelType::Cct, "cct"),
+        (ChannelType::Pressure, "pressure"),
+        (ChannelType::HumidityRelative, "humidityrelative"),
+        (ChannelType::Activity, "activity"),
+        (ChannelType::Steps, "steps"),
+        (ChannelType::Energy, "energy"),
+        (ChannelType::Distance, "distance"),
+        (ChannelType::Velocity, "velocity"),
+        (ChannelType::Concentration, "concentration"),
+        (ChannelType::Resistance, "resistance"),
+        (ChannelType::Ph, "ph"),
+        (ChannelType::UvIndex, "uvindex"),
+        (ChannelType::ElectricalConductivity, "electricalconductivity"),
+        (ChannelType::Count, "count"),
+        (ChannelType::Index, "index"),
+        (ChannelType::Gravity, "gravity"),
+        (ChannelType::PositionRelative, "positionrelative"),
+        (ChannelType::Phase, "phase"),
+        (ChannelType::MassConcentration, "massconcentration"),
+    ];
+
+    /// Gets the channel type from the value used by the C library.
+    ///
+    /// Any value that isn't recognized is returned as
+    /// [`ChannelType::Unknown`].
+    pub fn from_raw(val: ffi::iio_chan_type) -> Self {
+        Self::NAMES
+            .iter()
+            .find(|(typ, _)| *typ as ffi::iio_chan_type == val)
+            .map_or(ChannelType::Unknown, |(typ, _)| *typ)
+    }
+}
+
+impl fmt::Display for ChannelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = Self::NAMES
+            .iter()
+            .find(|(typ, _)| typ == self)
+            .map_or("unknown", |(_, name)| name);
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ChannelType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(typ, _)| *typ)
+            .ok_or(Error::StringConversionError)
+    }
+}
+
+/// The modifier of a channel, which further specifies the type of its data.
+///
+/// The `Display` and `FromStr` implementations use the names from the
+/// kernel ABI, as they appear in the sysfs attribute names, like "x" or
+/// "from_north_magnetic". A channel without a modifier has an empty name.
+#[allow(missing_docs)]
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    NoMod = ffi::iio_modifier_IIO_NO_MOD,
+    X = ffi::iio_modifier_IIO_MOD_X,
+    Y = ffi::iio_modifier_IIO_MOD_Y,
+    Z = ffi::iio_modifier_IIO_MOD_Z,
+    XAndY = ffi::iio_modifier_IIO_MOD_X_AND_Y,
+    XAndZ = ffi::iio_modifier_IIO_MOD_X_AND_Z,
+    YAndZ = ffi::iio_modifier_IIO_MOD_Y_AND_Z,
+    XAndYAndZ = ffi::iio_modifier_IIO_MOD_X_AND_Y_AND_Z,
+    XOrY = ffi::iio_modifier_IIO_MOD_X_OR_Y,
+    XOrZ = ffi::iio_modifier_IIO_MOD_X_OR_Z,
+    YOrZ = ffi::iio_modifier_IIO_MOD_Y_OR_Z,
+    XOrYOrZ = ffi::iio_modifier_IIO_MOD_X_OR_Y_OR_Z,
+    LightBoth = ffi::iio_modifier_IIO_MOD_LIGHT_BOTH,
+    LightIr = ffi::iio_modifier_IIO_MOD_LIGHT_IR,
+    RootSumSquaredXY = ffi::iio_modifier_IIO_MOD_ROOT_SUM_SQUARED_X_Y,
+    SumSquaredXYZ = ffi::iio_modifier_IIO_MOD_SUM_SQUARED_X_Y_Z,
+    LightClear = ffi::iio_modifier_IIO_MOD_LIGHT_CLEAR,
+    LightRed = ffi::iio_modifier_IIO_MOD_LIGHT_RED,
+    LightGreen = ffi::iio_modifier_IIO_MOD_LIGHT_GREEN,
+    LightBlue = ffi::iio_modifier_IIO_MOD_LIGHT_BLUE,
+    Quaternion = ffi::iio_modifier_IIO_MOD_QUATERNION,
+    TempAmbient = ffi::iio_modifier_IIO_MOD_TEMP_AMBIENT,
+    TempObject = ffi::iio_modifier_IIO_MOD_TEMP_OBJECT,
+    NorthMagn = ffi::iio_modifier_IIO_MOD_NORTH_MAGN,
+    NorthTrue = ffi::iio_modifier_IIO_MOD_NORTH_TRUE,
+    NorthMagnTiltComp = ffi::iio_modifier_IIO_MOD_NORTH_MAGN_TILT_COMP,
+    NorthTrueTiltComp = ffi::iio_modifier_IIO_MOD_NORTH_TRUE_TILT_COMP,
+    Running = ffi::iio_modifier_IIO_MOD_RUNNING,
+    Jogging = ffi::iio_modifier_IIO_MOD_JOGGING,
+    Walking = ffi::iio_modifier_IIO_MOD_WALKING,
+    Still = ffi::iio_modifier_IIO_MOD_STILL,
+    RootSumSquaredXYZ = ffi::iio_modifier_IIO_MOD_ROOT_SUM_SQUARED_X_Y_Z,
+    I = ffi::iio_modifier_IIO_MOD_I,
+    Q = ffi::iio_modifier_IIO_MOD_Q,
+    Co2 = ffi::iio_modifier_IIO_MOD_CO2,
+    Voc = ffi::iio_modifier_IIO_MOD_VOC,
+    LightUv = ffi::iio_modifier_IIO_MOD_LIGHT_UV,
+    LightDuv = ffi::iio_modifier_IIO_MOD_LIGHT_DUV,
+    Pm1 = ffi::iio_modifier_IIO_MOD_PM1,
+    Pm2p5 = ffi::iio_modifier_IIO_MOD_PM2P5,
+    Pm4 = ffi::iio_modifier_IIO_MOD_PM4,
+    Pm10 = ffi::iio_modifier_IIO_MOD_PM10,
+    Ethanol = ffi::iio_modifier_IIO_MOD_ETHANOL,
+    H2 = ffi::iio_modifier_IIO_MOD_H2,
+}
+
+impl Modifier {
+    /// The modifiers and their kernel ABI names.
+    const NAMES: &'static [(Modifier, &'static str)] = &[
+        (Modifier::NoMod, ""),
+        (Modifier::X, "x"),
+        (Modifier::Y, "y"),
+        (Modifier::Z, "z"),
+        (Modifier::XAndY, "x&y"),
+        (Modifier::XAndZ, "x&z"),
+        (Modifier::YAndZ, "y&z"),
+        (Modifier::XAndYAndZ, "x&y&z"),
+        (Modifier::XOrY, "x|y"),
+        (Modifier::XOrZ, "x|z"),
+        (Modifier::YOrZ, "y|z"),
+        (Modifier::XOrYOrZ, "x|y|z"),
+        (Modifier::LightBoth, "both"),
+        (Modifier::LightIr, "ir"),
+        (Modifier::RootSumSquaredXY, "sqrt(x^2+y^2)"),
+        (Modifier::SumSquaredXYZ, "x^2+y^2+z^2"),
+        (Modifier::LightClear, "clear"),
+        (Modifier::LightRed, "red"),
+        (Modifier::LightGreen, "green"),
+        (Modifier::LightBlue, "blue"),
+        (Modifier::Quaternion, "quaternion"),
+        (Modifier::TempAmbient, "ambient"),
+        (Modifier::TempObject, "object"),
+        (Modifier::NorthMagn, "from_north_magnetic"),
+        (Modifier::NorthTrue, "from_north_true"),
+        (Modifier::NorthMagnTiltComp, "from_north_magnetic_tilt_comp"),
+        (Modifier::NorthTrueTiltComp, "from_north_true_tilt_comp"),
+        (Modifier::Running, "running"),
+        (Modifier::Jogging, "jogging"),
+        (Modifier::Walking, "walking"),
+        (Modifier::Still, "still"),
+        (Modifier::RootSumSquaredXYZ, "sqrt(x^2+y^2+z^2)"),
+        (Modifier::I, "i"),
+        (Modifier::Q, "q"),
+        (Modifier::Co2, "co2"),
+        (Modifier::Voc, "voc"),
+        (Modifier::LightUv, "uv"),
+        (Modifier::LightDuv, "duv"),
+        (Modifier::Pm1, "pm1"),
+        (Modifier::Pm2p5, "pm2p5"),
+        (Modifier::Pm4, "pm4"),
+        (Modifier::Pm10, "pm10"),
+        (Modifier::Ethanol, "ethanol"),
+        (Modifier::H2, "h2"),
+    ];
+
+    /// Gets the modifier from the value used by the C library.
+    ///
+    /// Any value that isn't recognized is returned as
+    /// [`Modifier::NoMod`].
+    pub fn from_raw(val: ffi::iio_modifier) -> Self {
+        Self::NAMES
+            .iter()
+            .find(|(modifier, _)| *modifier as ffi::iio_modifier == val)
+            .map_or(Modifier::NoMod, |(modifier, _)| *modifier)
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = Self::NAMES
+            .iter()
+            .find(|(modifier, _)| modifier == self)
+            .map_or("", |(_, name)| name);
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(modifier, _)| *modifier)
+            .ok_or(Error::StringConversionError)
+    }
+}
+
+/// A sample value with a type that is determined at runtime.
+///
+/// This is used by generic tools, like loggers or plotters, that don't
+/// know the sample type of a channel at compile time. The integer types
+/// are chosen from the data format of the channel. The floating-point
+/// variants are never produced by this crate, since no data format maps
+/// to them, but they let an application hold the values that it has
+/// converted or scaled in the same type.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleValue {
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+}
+
+impl SampleValue {
+    /// Gets the value as a floating-point number.
+    pub fn as_f64(&self) -> f64 {
+        use SampleValue::*;
+        match *self {
+            I8(v) => f64::from(v),
+            U8(v) => f64::from(v),
+            I16(v) => f64::from(v),
+            U16(v) => f64::from(v),
+            I32(v) => f64::from(v),
+            U32(v) => f64::from(v),
+            I64(v) => v as f64,
+            U64(v) => v as f64,
+            F32(v) => f64::from(v),
+            F64(v) => v,
+        }
+    }
+}
+
+impl fmt::Display for SampleValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SampleValue::*;
+        match *self {
+            I8(v) => write!(f, "{}", v),
+            U8(v) => write!(f, "{}", v),
+            I16(v) => write!(f, "{}", v),
+            U16(v) => write!(f, "{}", v),
+            I32(v) => write!(f, "{}", v),
+            U32(v) => write!(f, "{}", v),
+            I64(v) => write!(f, "{}", v),
+            U64(v) => write!(f, "{}", v),
+            F32(v) => write!(f, "{}", v),
+            F64(v) => write!(f, "{}", v),
+        }
+    }
+}
+
 /// The format of a data sample.
 #[derive(Debug, Copy, Clone)]
 pub struct DataFormat {
@@ -124,6 +410,79 @@ impl DataFormat {
         nbytes as usize
     }
 
+    /// Decodes a single raw sample element, in the hardware format.
+    ///
+    /// This applies the byte order, shift, mask, and sign extension of the
+    /// format to the first `length` bits of `raw`. The result is returned
+    /// as the bits of a 64-bit integer, which, for signed formats, should
+    /// be interpreted as an `i64`. This returns `None` if the element is
+    /// larger than 64 bits, or if there isn't enough raw data.
+    pub(crate) fn decode(&self, raw: &[u8]) -> Option<u64> {
+        let nbytes = (self.length() / 8) as usize;
+        if !self.is_decodable() || raw.len() < nbytes {
+            return None;
+        }
+
+        let raw = &raw[..nbytes];
+        let mut val = if self.is_big_endian() {
+            raw.iter().fold(0u64, |v, b| (v << 8) | u64::from(*b))
+        }
+        else {
+            raw.iter().rev().fold(0u64, |v, b| (v << 8) | u64::from(*b))
+        };
+
+        val = val.checked_shr(self.shift()).unwrap_or(0);
+
+        let bits = self.bits();
+        if bits > 0 && bits < 64 {
+            let mask = (1u64 << bits) - 1;
+            val &= mask;
+            if self.is_signed() && (val >> (bits - 1)) & 1 != 0 {
+                val |= !mask;
+            }
+        }
+        Some(val)
+    }
+
+    /// Determines if the elements of the format can be decoded, which
+    /// requires them to be from 1 to 64 bits long.
+    pub(crate) fn is_decodable(&self) -> bool {
+        (1..=8).contains(&(self.length() / 8))
+    }
+
+    /// Decodes a single raw sample element, in the hardware format, into
+    /// a floating-point value, taking the sign into account.
+    pub(crate) fn decode_f64(&self, raw: &[u8]) -> Option<f64> {
+        let val = self.decode(raw)?;
+        if self.is_signed() {
+            Some(val as i64 as f64)
+        }
+        else {
+            Some(val as f64)
+        }
+    }
+
+    /// Decodes a single raw sample element, in the hardware format, into
+    /// a dynamically-typed value.
+    ///
+    /// The type of the value is an integer that matches the size and sign
+    /// of a single element of the format.
+    pub(crate) fn decode_value(&self, raw: &[u8]) -> Option<SampleValue> {
+        use SampleValue::*;
+        let val = self.decode(raw)?;
+        let val = match (self.length() / 8, self.is_signed()) {
+            (1, true) => I8(val as i8),
+            (1, false) => U8(val as u8),
+            (2, true) => I16(val as i16),
+            (2, false) => U16(val as u16),
+            (3..=4, true) => I32(val as i32),
+            (3..=4, false) => U32(val as u32),
+            (_, true) => I64(val as i64),
+            (_, false) => U64(val),
+        };
+        Some(val)
+    }
+
     /// Gets the `TypeId` for a single sample from the channel.
     ///
     /// This will get the `TypeId` for a sample if it can fit into a standard
@@ -152,7 +511,98 @@ impl DataFormat {
     }
 }
 
+/// A converter between the raw samples of a channel and physical values.
+///
+/// This is obtained from [`Channel::converter()`], which reads the scale
+/// and offset attributes of the channel once, so that samples can be
+/// converted in a tight loop without reading any attributes. The physical
+/// value of a raw sample is `(raw + offset) * scale`, in the units given
+/// by the IIO ABI for the type of channel.
+#[derive(Debug, Clone, Copy)]
+pub struct SampleConverter {
+    /// The data format of the channel
+    fmt: DataFormat,
+    /// The scale to apply to the raw samples
+    scale: f64,
+    /// The offset to apply to the raw samples, before scaling
+    offset: f64,
+}
+
+impl SampleConverter {
+    /// Creates a converter for the channel data format, with the specified
+    /// scale and offset.
+    pub fn new(fmt: DataFormat, scale: f64, offset: f64) -> Self {
+        Self { fmt, scale, offset }
+    }
+
+    /// Gets the scale applied to the raw samples.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Gets the offset applied to the raw samples.
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Converts a raw sample to a physical value.
+    ///
+    /// The sample should already be in the host format, as returned by
+    /// [`Channel::read()`], with the shift and sign extension applied.
+    pub fn to_si(&self, raw: i64) -> f64 {
+        (raw as f64 + self.offset) * self.scale
+    }
+
+    /// Converts a physical value to the nearest raw sample.
+    ///
+    /// The result is clamped to the range of values that fit in the
+    /// number of bits of the data format.
+    pub fn from_si(&self, val: f64) -> i64 {
+        let raw = (val / self.scale - self.offset).round();
+        let bits = self.fmt.bits();
+        if bits == 0 || bits >= 64 {
+            return raw as i64;
+        }
+
+        let (min, max) = if self.fmt.is_signed() {
+            (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1)
+        }
+        else {
+            (0, (1i64 << bits) - 1)
+        };
+        (raw as i64).max(min).min(max)
+    }
+
+    /// Decodes a single sample element in the hardware format, and
+    /// converts it to a physical value.
+    ///
+    /// This applies the byte order, shift, and sign extension of the data
+    /// format. It returns `None` if there isn't enough raw data, or the
+    /// element is larger than 64 bits.
+    pub fn decode(&self, raw: &[u8]) -> Option<f64> {
+        self.fmt
+            .decode_f64(raw)
+            .map(|val| (val + self.offset) * self.scale)
+    }
+}
+
+/// The calibration values for a channel.
+///
+/// These are held in the `calibbias` and `calibscale` attributes of the
+/// channel, and are applied internally by the hardware.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// The calibration bias, in units of the raw samples
+    pub bias: Option<i64>,
+    /// The calibration scale
+    pub scale: Option<f64>,
+}
+
 /// An Industrial I/O Device Channel
+///
+/// The direction of the channel is only known at runtime. To have it
+/// checked at compile time, convert it to an [`InputChannel`] or
+/// [`OutputChannel`].
 #[derive(Debug, Clone)]
 pub struct Channel {
     /// Pointer to the underlying IIO channel object
@@ -175,6 +625,29 @@ impl Channel {
         cstring_opt(pstr)
     }
 
+    /// Retrieves the label of the channel, if it has one.
+    ///
+    /// Labels are typically assigned to channels in the device tree, to
+    /// give them a meaningful name for the board. Like a device label, this
+    /// is read from the `label` attribute. See [`Device::label()`].
+    pub fn label(&self) -> Option<String> {
+        if !self.has_attr("label") {
+            return None;
+        }
+        self.attr_read_str("label")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Gets the device to which the channel belongs.
+    pub fn device(&self) -> Device {
+        Device {
+            dev: unsafe { ffi::iio_channel_get_device(self.chan) } as *mut _,
+            ctx: self.ctx.clone(),
+        }
+    }
+
     /// Determines if this is an output channel.
     pub fn is_output(&self) -> bool {
         unsafe { ffi::iio_channel_is_output(self.chan) }
@@ -225,6 +698,17 @@ impl Channel {
         cstring_opt(pstr)
     }
 
+    /// Gets the name of the file that holds the channel-specific attribute.
+    ///
+    /// For the local backend, this is the name of the file in the sysfs
+    /// directory of the device, like "in_voltage0_raw". It can be used to
+    /// set up inotify watches or udev rules for the attribute.
+    pub fn attr_filename(&self, attr: &str) -> Option<String> {
+        let cattr = cstring_or_bail!(attr);
+        let pstr = unsafe { ffi::iio_channel_attr_get_filename(self.chan, cattr.as_ptr()) };
+        cstring_opt(pstr)
+    }
+
     /// Reads a channel-specific attribute
     ///
     /// `attr` The name of the attribute
@@ -281,6 +765,16 @@ impl Channel {
         sys_result(ret, val)
     }
 
+    /// Reads the values accepted by a channel-specific attribute.
+    ///
+    /// This reads and parses the `<attr>_available` attribute for the
+    /// channel, as reported by the driver.
+    ///
+    /// `attr` The name of the attribute, like "scale"
+    pub fn attr_available(&self, attr: &str) -> Result<AttrChoices> {
+        self.attr_read(&format!("{}_available", attr))
+    }
+
     // Callback from the C lib to extract the collection of all
     // channel-specific attributes. See attr_read_all().
     unsafe extern "C" fn attr_read_all_cb(
@@ -323,6 +817,25 @@ impl Channel {
         self.attr_write_str(attr, &sval)
     }
 
+    /// Writes a channel-specific attribute, and reads it back.
+    ///
+    /// This returns both the requested value and the effective value that
+    /// the driver accepted, which may have been rounded or clamped.
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn attr_write_verified<T>(&self, attr: &str, val: T) -> Result<VerifiedWrite<T>>
+    where
+        T: ToAttribute + FromAttribute,
+    {
+        self.attr_write_str(attr, &val.to_attr()?)?;
+        let effective = self.attr_read(attr)?;
+        Ok(VerifiedWrite {
+            requested: val,
+            effective,
+        })
+    }
+
     /// Writes a channel-specific attribute as a string
     ///
     /// `attr` The name of the attribute
@@ -372,9 +885,15 @@ impl Channel {
     /// Enable the channel
     ///
     /// Before creating a buffer, at least one channel of the device
-    /// must be enabled.
-    pub fn enable(&self) {
+    /// must be enabled. Only scan elements can be enabled. For any other
+    /// channel, this fails with [`Error::NotScanElement`], rather than
+    /// leaving the error to be discovered when the buffer is created.
+    pub fn enable(&self) -> Result<()> {
+        if !self.is_scan_element() {
+            return Err(Error::NotScanElement);
+        }
         unsafe { ffi::iio_channel_enable(self.chan) };
+        Ok(())
     }
 
     /// Disable the channel
@@ -387,8 +906,182 @@ impl Channel {
         unsafe { ffi::iio_channel_is_enabled(self.chan) }
     }
 
+    // ----- Calibration -----
+
+    /// Determines if the channel has a calibration bias attribute.
+    pub fn has_calibbias(&self) -> bool {
+        self.has_attr("calibbias")
+    }
+
+    /// Gets the calibration bias of the channel.
+    ///
+    /// This is the value in the `calibbias` attribute, which is applied
+    /// internally by the hardware, in units of the raw samples.
+    pub fn calibbias(&self) -> Result<i64> {
+        self.attr_read_int("calibbias")
+    }
+
+    /// Sets the calibration bias of the channel.
+    pub fn set_calibbias(&self, val: i64) -> Result<()> {
+        self.attr_write_int("calibbias", val)
+    }
+
+    /// Determines if the channel has a calibration scale attribute.
+    pub fn has_calibscale(&self) -> bool {
+        self.has_attr("calibscale")
+    }
+
+    /// Gets the calibration scale of the channel.
+    ///
+    /// This is the value in the `calibscale` attribute, which is a gain
+    /// applied internally by the hardware.
+    pub fn calibscale(&self) -> Result<f64> {
+        self.attr_read_float("calibscale")
+    }
+
+    /// Sets the calibration scale of the channel.
+    pub fn set_calibscale(&self, val: f64) -> Result<()> {
+        self.attr_write_float("calibscale", val)
+    }
+
+    /// Gets the calibration values of the channel.
+    ///
+    /// Any calibration attribute that the channel doesn't have is `None`.
+    pub fn calibration(&self) -> Result<Calibration> {
+        let bias = if self.has_calibbias() {
+            Some(self.calibbias()?)
+        }
+        else {
+            None
+        };
+        let scale = if self.has_calibscale() {
+            Some(self.calibscale()?)
+        }
+        else {
+            None
+        };
+        Ok(Calibration { bias, scale })
+    }
+
+    /// Sets the calibration values of the channel.
+    ///
+    /// Only the values that are `Some` are written.
+    pub fn set_calibration(&self, cal: &Calibration) -> Result<()> {
+        if let Some(bias) = cal.bias {
+            self.set_calibbias(bias)?;
+        }
+        if let Some(scale) = cal.scale {
+            self.set_calibscale(scale)?;
+        }
+        Ok(())
+    }
+
+    // ----- Power Down -----
+
+    /// Determines if the channel can be powered down.
+    ///
+    /// This is typically an output channel of a DAC, with a `powerdown`
+    /// attribute.
+    pub fn has_powerdown(&self) -> bool {
+        self.has_attr("powerdown")
+    }
+
+    /// Determines if the channel is powered down.
+    pub fn is_powered_down(&self) -> Result<bool> {
+        self.attr_read_bool("powerdown")
+    }
+
+    /// Powers the channel down or up.
+    ///
+    /// When powered down, the output is in the state given by the power
+    /// down mode.
+    pub fn set_powerdown(&self, on: bool) -> Result<()> {
+        self.attr_write_bool("powerdown", on)
+    }
+
+    /// Gets the power down mode of the channel, like "1kohm_to_gnd".
+    pub fn powerdown_mode(&self) -> Result<String> {
+        self.attr_read_str("powerdown_mode")
+    }
+
+    /// Gets the power down modes that the channel supports.
+    pub fn powerdown_modes(&self) -> Result<Vec<String>> {
+        let s = self.attr_read_str("powerdown_mode_available")?;
+        Ok(s.split_whitespace().map(String::from).collect())
+    }
+
+    /// Sets the power down mode of the channel.
+    ///
+    /// If the channel has a `powerdown_mode_available` attribute, the mode
+    /// is checked against it first, and [`Error::ValueNotAvailable`] is
+    /// returned if the driver doesn't support it.
+    pub fn set_powerdown_mode(&self, mode: &str) -> Result<()> {
+        if self.has_attr("powerdown_mode_available")
+            && !self.powerdown_modes()?.iter().any(|m| m == mode)
+        {
+            return Err(Error::ValueNotAvailable);
+        }
+        self.attr_write_str("powerdown_mode", mode)
+    }
+
+    /// Parks the output, by setting the power down mode, if one is given,
+    /// and then powering the channel down.
+    pub fn park(&self, mode: Option<&str>) -> Result<()> {
+        if let Some(mode) = mode {
+            self.set_powerdown_mode(mode)?;
+        }
+        self.set_powerdown(true)
+    }
+
     // ----- Data Type and Conversion -----
 
+    /// Gets the scale and offset to convert raw samples from the channel
+    /// into physical values.
+    ///
+    /// These come from the channel's `scale` and `offset` attributes. If
+    /// either attribute doesn't exist, the scale defaults to 1.0 and the
+    /// offset to 0.0.
+    pub fn scale_offset(&self) -> Result<(f64, f64)> {
+        let scale = if self.has_attr("scale") {
+            self.attr_read_float("scale")?
+        }
+        else {
+            1.0
+        };
+        let offset = if self.has_attr("offset") {
+            self.attr_read_float("offset")?
+        }
+        else {
+            0.0
+        };
+        Ok((scale, offset))
+    }
+
+    /// Reads the current value of the channel in physical units.
+    ///
+    /// If the driver provides an `input` attribute with the processed
+    /// value, that is returned directly. Otherwise this reads the `raw`
+    /// attribute and applies the scale and offset, as in the kernel ABI:
+    ///   `(raw + offset) * scale`
+    pub fn read_processed(&self) -> Result<f64> {
+        if self.has_attr("input") {
+            return self.attr_read_float("input");
+        }
+        let raw = self.attr_read_float("raw")?;
+        let (scale, offset) = self.scale_offset()?;
+        Ok((raw + offset) * scale)
+    }
+
+    /// Gets a converter between the raw samples of the channel and
+    /// physical values.
+    ///
+    /// This reads the scale and offset of the channel once, with
+    /// [`Channel::scale_offset()`].
+    pub fn converter(&self) -> Result<SampleConverter> {
+        let (scale, offset) = self.scale_offset()?;
+        Ok(SampleConverter::new(self.data_format(), scale, offset))
+    }
+
     /// Gets the data format for the channel
     pub fn data_format(&self) -> DataFormat {
         unsafe {
@@ -408,11 +1101,12 @@ impl Channel {
 
     /// Gets the type of data associated with the channel
     pub fn channel_type(&self) -> ChannelType {
-        // TODO: We're trusting that the lib returns a valid enum.
-        unsafe {
-            let n = ffi::iio_channel_get_type(self.chan);
-            mem::transmute(n)
-        }
+        ChannelType::from_raw(unsafe { ffi::iio_channel_get_type(self.chan) })
+    }
+
+    /// Gets the modifier of the channel, if any.
+    pub fn modifier(&self) -> Modifier {
+        Modifier::from_raw(unsafe { ffi::iio_channel_get_modifier(self.chan) })
     }
 
     /// Converts a single sample from the hardware format to the host format.
@@ -460,61 +1154,103 @@ impl Channel {
     }
 
     /// Demultiplex and convert the samples of a given channel.
-    pub fn read<T>(&self, buf: &Buffer) -> Result<Vec<T>>
+    pub fn read<T>(&self, buf: &dyn BufferHandle) -> Result<Vec<T>>
     where
         T: Default + Copy + 'static,
     {
-        if self.type_of() != Some(TypeId::of::<T>()) {
-            return Err(Error::WrongDataType);
-        }
-
-        let n = buf.capacity();
-        let sz_item = mem::size_of::<T>();
-        let sz_in = n * sz_item;
-
-        let mut v = vec![T::default(); n];
-        let sz = unsafe { ffi::iio_channel_read(self.chan, buf.buf, v.as_mut_ptr().cast(), sz_in) };
-
-        if sz > sz_in {
-            return Err(Error::BadReturnSize); // This should never happen.
-        }
-
-        if sz < sz_in {
-            v.truncate(sz / sz_item);
-        }
+        let mut v = vec![T::default(); buf.capacity()];
+        let n = self.read_into(buf, &mut v)?;
+        v.truncate(n);
         Ok(v)
     }
 
     /// Demultiplex the samples of a given channel.
-    pub fn read_raw<T>(&self, buf: &Buffer) -> Result<Vec<T>>
+    pub fn read_raw<T>(&self, buf: &dyn BufferHandle) -> Result<Vec<T>>
     where
         T: Default + Copy + 'static,
+    {
+        let mut v = vec![T::default(); buf.capacity()];
+        let n = self.read_raw_into(buf, &mut v)?;
+        v.truncate(n);
+        Ok(v)
+    }
+
+    /// Demultiplex and convert the samples of a given channel into a
+    /// caller-provided slice.
+    ///
+    /// This does not allocate, so it can be used in tight, real-time
+    /// loops, reusing the same destination for each refill of the buffer.
+    /// It reads up to the length of the slice, and returns the number of
+    /// items written.
+    pub fn read_into<T>(&self, buf: &dyn BufferHandle, data: &mut [T]) -> Result<usize>
+    where
+        T: Copy + 'static,
     {
         if self.type_of() != Some(TypeId::of::<T>()) {
             return Err(Error::WrongDataType);
         }
+        let sz_item = mem::size_of::<T>();
+        let sz = self.read_bytes_into(buf, unsafe {
+            slice::from_raw_parts_mut(data.as_mut_ptr().cast(), mem::size_of_val(data))
+        })?;
+        Ok(sz / sz_item)
+    }
 
-        let n = buf.capacity();
+    /// Demultiplex the samples of a given channel into a caller-provided
+    /// slice.
+    ///
+    /// This is the same as [`Channel::read_into()`], except that the
+    /// samples are left in the hardware format.
+    pub fn read_raw_into<T>(&self, buf: &dyn BufferHandle, data: &mut [T]) -> Result<usize>
+    where
+        T: Copy + 'static,
+    {
+        if self.type_of() != Some(TypeId::of::<T>()) {
+            return Err(Error::WrongDataType);
+        }
         let sz_item = mem::size_of::<T>();
-        let sz_in = n * sz_item;
+        let sz = self.read_raw_bytes_into(buf, unsafe {
+            slice::from_raw_parts_mut(data.as_mut_ptr().cast(), mem::size_of_val(data))
+        })?;
+        Ok(sz / sz_item)
+    }
 
-        let mut v = vec![T::default(); n];
-        let sz =
-            unsafe { ffi::iio_channel_read_raw(self.chan, buf.buf, v.as_mut_ptr().cast(), sz_in) };
+    /// Demultiplex and convert the samples of a given channel into a
+    /// caller-provided byte slice.
+    ///
+    /// This returns the number of bytes written.
+    pub fn read_bytes_into(&self, buf: &dyn BufferHandle, data: &mut [u8]) -> Result<usize> {
+        let sz_in = data.len();
+        let pdata = data.as_mut_ptr().cast();
+        let sz = unsafe { ffi::iio_channel_read(self.chan, buf.as_raw(), pdata, sz_in) };
 
         if sz > sz_in {
             return Err(Error::BadReturnSize); // This should never happen.
         }
+        Ok(sz)
+    }
+
+    /// Demultiplex the samples of a given channel into a caller-provided
+    /// byte slice, leaving them in the hardware format.
+    ///
+    /// This returns the number of bytes written.
+    pub fn read_raw_bytes_into(&self, buf: &dyn BufferHandle, data: &mut [u8]) -> Result<usize> {
+        let sz_in = data.len();
+        let pdata = data.as_mut_ptr().cast();
+        let sz = unsafe { ffi::iio_channel_read_raw(self.chan, buf.as_raw(), pdata, sz_in) };
 
-        if sz < sz_in {
-            v.truncate(sz / sz_item);
+        if sz > sz_in {
+            return Err(Error::BadReturnSize); // This should never happen.
         }
-        Ok(v)
+        Ok(sz)
     }
 
     /// Convert and multiplex the samples of a given channel.
+    ///
+    /// The samples are converted from the host format to the format used
+    /// by the hardware, as described by the channel's data format.
     /// Returns the number of items written.
-    pub fn write<T>(&self, buf: &Buffer, data: &[T]) -> Result<usize>
+    pub fn write<T>(&self, buf: &dyn BufferHandle, data: &[T]) -> Result<usize>
     where
         T: Default + Copy + 'static,
     {
@@ -525,14 +1261,17 @@ impl Channel {
         let sz_item = mem::size_of::<T>();
         let sz_in = data.len() * sz_item;
 
-        let sz = unsafe { ffi::iio_channel_write(self.chan, buf.buf, data.as_ptr().cast(), sz_in) };
+        let pdata = data.as_ptr().cast();
+        let sz = unsafe { ffi::iio_channel_write(self.chan, buf.as_raw(), pdata, sz_in) };
 
         Ok(sz / sz_item)
     }
 
-    /// Multiplex the samples of a given channel.
+    /// Multiplex the samples of a given channel, without converting them.
+    ///
+    /// The samples should already be in the format used by the hardware.
     /// Returns the number of items written.
-    pub fn write_raw<T>(&self, buf: &Buffer, data: &[T]) -> Result<usize>
+    pub fn write_raw<T>(&self, buf: &dyn BufferHandle, data: &[T]) -> Result<usize>
     where
         T: Default + Copy + 'static,
     {
@@ -543,10 +1282,208 @@ impl Channel {
         let sz_item = mem::size_of::<T>();
         let sz_in = data.len() * sz_item;
 
-        let sz = unsafe { ffi::iio_channel_write(self.chan, buf.buf, data.as_ptr().cast(), sz_in) };
+        let pdata = data.as_ptr().cast();
+        let sz = unsafe { ffi::iio_channel_write_raw(self.chan, buf.as_raw(), pdata, sz_in) };
 
         Ok(sz / sz_item)
     }
+
+    /// Creates a channel from a raw C channel pointer.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be a valid, non-NULL, channel of a device that
+    /// belongs to the specified context.
+    pub unsafe fn from_raw(ctx: &Context, chan: *mut ffi::iio_channel) -> Self {
+        Self {
+            chan,
+            ctx: ctx.clone(),
+        }
+    }
+
+    /// Gets the raw pointer to the underlying C channel.
+    ///
+    /// The channel is owned by its device, and the pointer is only valid
+    /// while a reference to the context is alive.
+    pub fn as_raw(&self) -> *mut ffi::iio_channel {
+        self.chan
+    }
+
+    /// Converts this into a channel that can only be used for input.
+    ///
+    /// This fails with [`Error::WrongDirection`] if it is an output channel.
+    pub fn into_input(self) -> Result<InputChannel> {
+        if self.is_output() {
+            return Err(Error::WrongDirection);
+        }
+        Ok(TypedChannel {
+            chan: self,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Converts this into a channel that can only be used for output.
+    ///
+    /// This fails with [`Error::WrongDirection`] if it is an input channel.
+    pub fn into_output(self) -> Result<OutputChannel> {
+        if !self.is_output() {
+            return Err(Error::WrongDirection);
+        }
+        Ok(TypedChannel {
+            chan: self,
+            phantom: PhantomData,
+        })
+    }
+}
+
+/// A channel with a direction that is checked at compile time.
+///
+/// The direction is given by the type parameter, `D`, which is either the
+/// [`Input`] or [`Output`] marker type. An input channel can only be read
+/// from an input buffer, and an output channel can only be written to an
+/// output buffer. The untyped [`Channel`] is available for everything else,
+/// like getting the attributes of the channel.
+#[derive(Debug, Clone)]
+pub struct TypedChannel<D> {
+    /// The untyped channel
+    chan: Channel,
+    /// The direction of the channel
+    phantom: PhantomData<D>,
+}
+
+/// A channel that can only be used for input (capture) from the hardware.
+pub type InputChannel = TypedChannel<Input>;
+
+/// A channel that can only be used for output to the hardware.
+pub type OutputChannel = TypedChannel<Output>;
+
+impl<D> TypedChannel<D> {
+    /// Gets a reference to the untyped channel.
+    pub fn channel(&self) -> &Channel {
+        &self.chan
+    }
+
+    /// Converts this back into an untyped channel.
+    pub fn into_channel(self) -> Channel {
+        self.chan
+    }
+
+    /// Enable the channel
+    ///
+    /// See [`Channel::enable()`].
+    pub fn enable(&self) -> Result<()> {
+        self.chan.enable()
+    }
+
+    /// Disable the channel
+    pub fn disable(&self) {
+        self.chan.disable()
+    }
+
+    /// Determines if the channel is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.chan.is_enabled()
+    }
+}
+
+impl TypedChannel<Input> {
+    /// Reads the current value of the channel in physical units.
+    ///
+    /// See [`Channel::read_processed()`].
+    pub fn read_processed(&self) -> Result<f64> {
+        self.chan.read_processed()
+    }
+
+    /// Demultiplex and convert the samples of the channel.
+    ///
+    /// See [`Channel::read()`].
+    pub fn read<T, B>(&self, buf: &Buffer<B>) -> Result<Vec<T>>
+    where
+        T: Default + Copy + 'static,
+        B: InputDirection,
+    {
+        self.chan.read(buf)
+    }
+
+    /// Demultiplex the samples of the channel.
+    ///
+    /// See [`Channel::read_raw()`].
+    pub fn read_raw<T, B>(&self, buf: &Buffer<B>) -> Result<Vec<T>>
+    where
+        T: Default + Copy + 'static,
+        B: InputDirection,
+    {
+        self.chan.read_raw(buf)
+    }
+
+    /// Demultiplex and convert the samples of the channel into a
+    /// caller-provided slice.
+    ///
+    /// See [`Channel::read_into()`].
+    pub fn read_into<T, B>(&self, buf: &Buffer<B>, data: &mut [T]) -> Result<usize>
+    where
+        T: Copy + 'static,
+        B: InputDirection,
+    {
+        self.chan.read_into(buf, data)
+    }
+
+    /// Demultiplex the samples of the channel into a caller-provided slice.
+    ///
+    /// See [`Channel::read_raw_into()`].
+    pub fn read_raw_into<T, B>(&self, buf: &Buffer<B>, data: &mut [T]) -> Result<usize>
+    where
+        T: Copy + 'static,
+        B: InputDirection,
+    {
+        self.chan.read_raw_into(buf, data)
+    }
+}
+
+impl TypedChannel<Output> {
+    /// Convert and multiplex the samples of the channel.
+    ///
+    /// See [`Channel::write()`].
+    pub fn write<T, B>(&self, buf: &Buffer<B>, data: &[T]) -> Result<usize>
+    where
+        T: Default + Copy + 'static,
+        B: OutputDirection,
+    {
+        self.chan.write(buf, data)
+    }
+
+    /// Multiplex the samples of the channel, without converting them.
+    ///
+    /// See [`Channel::write_raw()`].
+    pub fn write_raw<T, B>(&self, buf: &Buffer<B>, data: &[T]) -> Result<usize>
+    where
+        T: Default + Copy + 'static,
+        B: OutputDirection,
+    {
+        self.chan.write_raw(buf, data)
+    }
+}
+
+// The Channel can be sent to another thread.
+unsafe impl Send for Channel {}
+
+impl PartialEq for Channel {
+    /// Two channels are the same if they refer to the same underlying
+    /// object in the library. Within a context, this is the channel with
+    /// the same ID and direction in the same device.
+    fn eq(&self, other: &Self) -> bool {
+        self.chan == other.chan
+    }
+}
+
+impl Eq for Channel {}
+
+impl Hash for Channel {
+    /// Hashes the identity of the underlying object, to be consistent with
+    /// the equality comparison.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.chan.hash(state)
+    }
 }
 
 /// Iterator over the attributes of a Channel
@@ -584,6 +1521,22 @@ impl<'a> Iterator for AttrIterator<'a> {
 mod tests {
     use super::*;
 
+    // The type and modifier names match the kernel ABI.
+    #[test]
+    fn type_and_modifier_names() {
+        assert_eq!(ChannelType::AnglVel.to_string(), "anglvel");
+        assert_eq!(ChannelType::Light.to_string(), "illuminance");
+        assert_eq!("voltage".parse::<ChannelType>().unwrap(), ChannelType::Voltage);
+        assert!("bogus".parse::<ChannelType>().is_err());
+        assert_eq!(ChannelType::from_raw(ffi::iio_chan_type_IIO_TEMP), ChannelType::Temp);
+        assert_eq!(ChannelType::from_raw(12345), ChannelType::Unknown);
+
+        assert_eq!(Modifier::XAndY.to_string(), "x&y");
+        assert_eq!(Modifier::NoMod.to_string(), "");
+        assert_eq!("from_north_true".parse::<Modifier>().unwrap(), Modifier::NorthTrue);
+        assert_eq!(Modifier::from_raw(ffi::iio_modifier_IIO_MOD_PM2P5), Modifier::Pm2p5);
+    }
+
     // See that we get the default context.
     #[test]
     fn default_context() {
@@ -591,4 +1544,64 @@ mod tests {
         let chan = dev.get_channel(0);
         assert!(chan.is_ok());
     }
+
+    // Makes a data format for testing sample decoding.
+    fn data_fmt(length: u32, bits: u32, shift: u32, is_signed: bool, is_be: bool) -> DataFormat {
+        DataFormat::new(ffi::iio_data_format {
+            length,
+            bits,
+            shift,
+            is_signed,
+            is_fully_defined: false,
+            is_be,
+            with_scale: false,
+            scale: 0.0,
+            repeat: 1,
+        })
+    }
+
+    // Decode a signed, 12-bit sample, stored in 16 bits, shifted by 4.
+    #[test]
+    fn decode_sample() {
+        let fmt = data_fmt(16, 12, 4, true, false);
+        assert_eq!(fmt.decode(&0x7ff0u16.to_le_bytes()), Some(0x7ff));
+        assert_eq!(fmt.decode_f64(&0x8000u16.to_le_bytes()), Some(-2048.0));
+
+        let fmt = data_fmt(16, 12, 4, false, true);
+        assert_eq!(fmt.decode(&0xfff0u16.to_be_bytes()), Some(0xfff));
+
+        assert_eq!(fmt.decode(&[0u8]), None);
+    }
+
+    // Decoded values get the type from the data format.
+    #[test]
+    fn decode_sample_value() {
+        let fmt = data_fmt(16, 12, 4, true, false);
+        let val = fmt.decode_value(&0x8000u16.to_le_bytes());
+        assert_eq!(val, Some(SampleValue::I16(-2048)));
+        assert_eq!(val.unwrap().as_f64(), -2048.0);
+
+        let fmt = data_fmt(8, 8, 0, false, false);
+        assert_eq!(fmt.decode_value(&[200]), Some(SampleValue::U8(200)));
+        assert_eq!(format!("{}", SampleValue::U8(200)), "200");
+
+        // Formats that are empty, or wider than 64 bits, can't be decoded
+        assert!(fmt.is_decodable());
+        assert!(!data_fmt(0, 0, 0, false, false).is_decodable());
+        assert!(!data_fmt(128, 128, 0, false, false).is_decodable());
+        assert_eq!(data_fmt(128, 128, 0, false, false).decode_value(&[0; 16]), None);
+    }
+
+    // The converter applies the offset and scale, and clamps to the range
+    // of the format.
+    #[test]
+    fn sample_converter() {
+        let conv = SampleConverter::new(data_fmt(16, 12, 4, true, false), 0.5, 10.0);
+        assert_eq!(conv.to_si(-10), 0.0);
+        assert_eq!(conv.to_si(90), 50.0);
+        assert_eq!(conv.from_si(50.0), 90);
+        assert_eq!(conv.from_si(1.0e6), 2047);
+        assert_eq!(conv.from_si(-1.0e6), -2048);
+        assert_eq!(conv.decode(&0x0100u16.to_le_bytes()), Some(13.0));
+    }
 }
@@ -10,16 +10,32 @@
 //! Industrial I/O Contexts.
 //!
 
-use crate::{cstring_opt, ffi, sys_result, Device, Error, Result, Version};
+use crate::{
+    cstring_opt, device::UserData, ffi, sys_result, Device, Error, FromAttribute, Result, Version,
+};
 use nix::errno::{self, Errno};
 use std::{
+    collections::HashMap,
     ffi::{CStr, CString},
+    mem,
+    net::SocketAddr,
     os::raw::{c_char, c_uint},
     ptr, slice, str,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
     time::Duration,
 };
 
+/// The TCP port on which the IIO daemon listens, by default.
+pub const DEFAULT_IIOD_PORT: u16 = 30431;
+
+/// The I/O timeout value that specifies that no timeout should be used.
+///
+/// With this, I/O operations on a context block until they complete.
+pub const NO_TIMEOUT: Duration = Duration::from_secs(0);
+
 /// An Industrial I/O Context
 ///
 /// Since the IIO library isn't thread safe, this object cannot be Send or
@@ -102,12 +118,44 @@ pub enum Backend<'a> {
     Local,
 }
 
+impl<'a> Backend<'a> {
+    /// Gets the name of the backend in the C library, like "usb", as used
+    /// by [`has_backend()`](crate::has_backend).
+    ///
+    /// This is `None` for the default and URI backends, which are resolved
+    /// by the library when the context is created.
+    pub fn name(&self) -> Option<&'static str> {
+        use Backend::*;
+        match *self {
+            Default | Uri(_) => None,
+            Xml(_) | XmlMem(_) => Some("xml"),
+            Network(_) => Some("ip"),
+            Usb(_) => Some("usb"),
+            Serial(_) => Some("serial"),
+            #[cfg(target_os = "linux")]
+            Local => Some("local"),
+        }
+    }
+}
+
 /// This holds a pointer to the library context.
 /// When it is dropped, the library context is destroyed.
 #[derive(Debug)]
 pub struct InnerContext {
     /// Pointer to a libiio Context object
     pub(crate) ctx: *mut ffi::iio_context,
+    /// The current I/O timeout for the context, in ms.
+    /// The C library has no way to query this, so we track it here.
+    timeout_ms: AtomicU64,
+    /// The user data attached to the devices in the context, by device.
+    ///
+    /// This is kept here, rather than in the data pointers of the C
+    /// devices, since those may belong to other C code that shares the
+    /// context.
+    user_data: Mutex<HashMap<*mut ffi::iio_device, UserData>>,
+    /// Lock for changes to the I/O timeout, so that a temporary override
+    /// is set and restored as a single operation.
+    timeout_lock: Mutex<()>,
 }
 
 impl InnerContext {
@@ -120,7 +168,24 @@ impl InnerContext {
             Err(Error::from(Errno::last()))
         }
         else {
-            Ok(Self { ctx })
+            let timeout_ms = AtomicU64::new(Self::default_timeout_ms(ctx));
+            Ok(Self {
+                ctx,
+                timeout_ms,
+                user_data: Mutex::new(HashMap::new()),
+                timeout_lock: Mutex::new(()),
+            })
+        }
+    }
+
+    /// Gets the I/O timeout, in ms, that the C library uses by default for
+    /// the backend of a newly-created context.
+    fn default_timeout_ms(ctx: *mut ffi::iio_context) -> u64 {
+        let name = cstring_opt(unsafe { ffi::iio_context_get_name(ctx) }).unwrap_or_default();
+        match name.as_str() {
+            "network" | "usb" => 5000,
+            "local" | "serial" => 1000,
+            _ => 0,
         }
     }
 
@@ -129,8 +194,51 @@ impl InnerContext {
     /// This creates a full copy of the actual context held in the underlying
     /// C library. This is useful if you want to give a separate copy to each
     /// thread in an application, which could help performance.
+    ///
+    /// The copy starts with the same I/O timeout as this context.
     pub fn try_clone(&self) -> Result<Self> {
-        Self::new(unsafe { ffi::iio_context_clone(self.ctx) })
+        let inner = Self::new(unsafe { ffi::iio_context_clone(self.ctx) })?;
+        let ms = self.timeout_ms.load(Ordering::Relaxed);
+        if ms != inner.timeout_ms.load(Ordering::Relaxed) {
+            let ret = unsafe { ffi::iio_context_set_timeout(inner.ctx, ms as c_uint) };
+            sys_result(ret, ())?;
+            inner.timeout_ms.store(ms, Ordering::Relaxed);
+        }
+        Ok(inner)
+    }
+
+    /// Creates the inner context from a raw C context pointer.
+    ///
+    /// The inner context takes ownership of the C context, and destroys it
+    /// when dropped. An error is returned if the pointer is NULL.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be a valid context from the C library that is not
+    /// owned or destroyed by anything else.
+    pub unsafe fn from_raw(ctx: *mut ffi::iio_context) -> Result<Self> {
+        Self::new(ctx)
+    }
+
+    /// Gets the raw pointer to the underlying C context.
+    ///
+    /// This can be passed to other C libraries that work with IIO
+    /// contexts. It remains owned by this object.
+    pub fn as_raw(&self) -> *mut ffi::iio_context {
+        self.ctx
+    }
+
+    /// Consumes the inner context, returning the raw pointer to the
+    /// underlying C context.
+    ///
+    /// The C context is _not_ destroyed. The caller becomes responsible
+    /// for destroying it, perhaps with [`InnerContext::from_raw()`]. Any
+    /// user data attached to the devices is dropped.
+    pub fn into_raw(self) -> *mut ffi::iio_context {
+        self.user_data.lock().unwrap_or_else(|err| err.into_inner()).clear();
+        let ctx = self.ctx;
+        mem::forget(self);
+        ctx
     }
 }
 
@@ -203,7 +311,8 @@ impl Context {
     /// let ctx = iio::Context::with_backend(iio::Backend::Uri("ip:192.168.2.1"));
     /// ```
     pub fn with_backend(be: Backend) -> Result<Self> {
-        Self::from_ptr(unsafe {
+        let name = be.name();
+        let res = Self::from_ptr(unsafe {
             match be {
                 Backend::Default => ffi::iio_create_default_context(),
                 Backend::Xml(name) => {
@@ -234,7 +343,32 @@ impl Context {
                 #[cfg(target_os = "linux")]
                 Backend::Local => ffi::iio_create_local_context(),
             }
-        })
+        });
+
+        match name {
+            Some(name) if res.is_err() && !crate::has_backend(name) => Err(Error::General(
+                format!("The library was built without the {} backend", name),
+            )),
+            _ => res,
+        }
+    }
+
+    /// Gets a builder to create a context with explicit options.
+    ///
+    /// See [`ContextBuilder`].
+    pub fn builder<'a>() -> ContextBuilder<'a> {
+        ContextBuilder::new()
+    }
+
+    /// Creates a context from an XML description, like the one from
+    /// [`Context::to_xml()`].
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        Self::with_backend(Backend::XmlMem(xml))
+    }
+
+    /// Creates a context from an XML description in a file.
+    pub fn from_xml_file(path: &str) -> Result<Self> {
+        Self::with_backend(Backend::Xml(path))
     }
 
     /// Creates a context specified by the `uri`.
@@ -250,11 +384,54 @@ impl Context {
         Self::with_backend(Backend::Network(hostname))
     }
 
+    /// Creates a network backend on the specified host and port.
+    ///
+    /// The host can be a hostname, or an IPv4 or IPv6 address. An IPv6
+    /// address can be given with or without the square brackets that are
+    /// needed to separate it from the port in a URI.
+    ///
+    /// ```no_run
+    /// use industrial_io as iio;
+    ///
+    /// let ctx = iio::Context::from_network_port("fe80::1%eth0", iio::DEFAULT_IIOD_PORT);
+    /// ```
+    pub fn from_network_port(host: &str, port: u16) -> Result<Self> {
+        Self::with_backend(Backend::Network(&network_host(host, port)))
+    }
+
+    /// Creates a network backend for the specified socket address.
+    pub fn from_network_addr(addr: SocketAddr) -> Result<Self> {
+        Self::with_backend(Backend::Network(&addr.to_string()))
+    }
+
     /// Creates a context from an existing "inner" object.
     pub fn from_inner(inner: InnerContext) -> Self {
         Self::from(inner)
     }
 
+    /// Creates a context from a raw C context pointer.
+    ///
+    /// The context takes ownership of the C context, and destroys it when
+    /// the last reference to it is dropped. An error is returned if the
+    /// pointer is NULL.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be a valid context from the C library that is not
+    /// owned or destroyed by anything else.
+    pub unsafe fn from_raw(ctx: *mut ffi::iio_context) -> Result<Self> {
+        Self::from_ptr(ctx)
+    }
+
+    /// Gets the raw pointer to the underlying C context.
+    ///
+    /// This can be passed to other C libraries that work with IIO
+    /// contexts, such as _libad9361_. It remains owned by this object, and
+    /// is only valid while a reference to the context is alive.
+    pub fn as_raw(&self) -> *mut ffi::iio_context {
+        self.inner.ctx
+    }
+
     /// Creates a Rust Context object from a C context pointer.
     fn from_ptr(ctx: *mut ffi::iio_context) -> Result<Self> {
         let inner = InnerContext::new(ctx)?;
@@ -282,7 +459,24 @@ impl Context {
     }
 
     /// Make a new context based on a full copy of underlying C context.
+    ///
+    /// This is the same as [`Context::try_clone()`].
     pub fn try_deep_clone(&self) -> Result<Self> {
+        self.try_clone()
+    }
+
+    /// Tries to make an independent copy of the context.
+    ///
+    /// Unlike [`Clone::clone()`], which gives another reference to the same
+    /// context, this makes a full copy of the underlying C context. For a
+    /// network or USB context, the copy has its own connection, and its
+    /// own I/O timeout, which starts at the same value as this one. So a
+    /// long, blocking transfer on one thread doesn't hold up attribute
+    /// reads on another thread that uses a copy.
+    ///
+    /// Devices and channels from the copy are distinct from those of this
+    /// context.
+    pub fn try_clone(&self) -> Result<Self> {
         let inner = self.inner.try_clone()?;
         Ok(Self {
             inner: Arc::new(inner),
@@ -330,7 +524,20 @@ impl Context {
     }
 
     /// Obtain the XML representation of the context.
+    ///
+    /// This is the same as [`Context::to_xml()`].
     pub fn xml(&self) -> String {
+        self.to_xml()
+    }
+
+    /// Gets the XML representation of the context.
+    ///
+    /// This describes the devices, channels, and attributes of the
+    /// context, along with the values of the context attributes. It can
+    /// be saved as a snapshot of the hardware configuration, and loaded
+    /// with [`Context::from_xml()`] to inspect the hardware offline. The
+    /// values of the device and channel attributes are not included.
+    pub fn to_xml(&self) -> String {
         let pstr = unsafe { ffi::iio_context_get_xml(self.inner.ctx) };
         cstring_opt(pstr).unwrap_or_default()
     }
@@ -358,7 +565,7 @@ impl Context {
             ffi::iio_context_get_attr(self.inner.ctx, idx as c_uint, &mut pname, &mut pval)
         };
         if ret < 0 {
-            return Err(errno::from_i32(ret).into());
+            return Err(errno::from_i32(-ret).into());
         }
         let name = cstring_opt(pname);
         let val = cstring_opt(pval);
@@ -368,18 +575,96 @@ impl Context {
         Ok((name.unwrap(), val.unwrap()))
     }
 
+    /// Reads a context-specific attribute, by name, as a typed value.
+    ///
+    /// Context attributes are read-only. They are set by the backend when
+    /// the context is created.
+    pub fn attr_read<T: FromAttribute>(&self, attr: &str) -> Result<T> {
+        let sval = self.attr_read_str(attr)?;
+        T::from_attr(&sval)
+    }
+
+    /// Reads a context-specific attribute, by name, as a string.
+    pub fn attr_read_str(&self, attr: &str) -> Result<String> {
+        let attr = CString::new(attr)?;
+        let pstr = unsafe { ffi::iio_context_get_attr_value(self.inner.ctx, attr.as_ptr()) };
+        cstring_opt(pstr).ok_or_else(|| Errno::ENOENT.into())
+    }
+
     /// Gets an iterator for the attributes in the context
     pub fn attributes(&self) -> AttrIterator {
         AttrIterator { ctx: self, idx: 0 }
     }
 
+    /// Reads a context attribute that may not exist.
+    fn attr_opt(&self, attr: &str) -> Option<String> {
+        self.attr_read_str(attr).ok()
+    }
+
+    /// Gets the URI of the context, like "ip:192.168.2.1", from the `uri`
+    /// attribute.
+    pub fn uri(&self) -> Option<String> {
+        self.attr_opt("uri")
+    }
+
+    /// Gets the IP address of a network context, from the `ip,ip-addr`
+    /// attribute.
+    pub fn ip_address(&self) -> Option<String> {
+        self.attr_opt("ip,ip-addr")
+    }
+
+    /// Gets the vendor of a USB context, from the `usb,vendor` attribute.
+    pub fn usb_vendor(&self) -> Option<String> {
+        self.attr_opt("usb,vendor")
+    }
+
+    /// Gets the product of a USB context, from the `usb,product`
+    /// attribute.
+    pub fn usb_product(&self) -> Option<String> {
+        self.attr_opt("usb,product")
+    }
+
+    /// Gets the version of the firmware of the hardware, from the
+    /// `fw_version` attribute.
+    pub fn fw_version(&self) -> Option<String> {
+        self.attr_opt("fw_version")
+    }
+
+    /// Gets the model of the hardware, from the `hw_model` attribute.
+    pub fn hw_model(&self) -> Option<String> {
+        self.attr_opt("hw_model")
+    }
+
+    /// Gets the serial number of the hardware, from the `hw_serial`
+    /// attribute.
+    pub fn hw_serial(&self) -> Option<String> {
+        self.attr_opt("hw_serial")
+    }
+
+    /// Gets the version of the kernel running on the hardware, from the
+    /// `local,kernel` attribute.
+    pub fn kernel_version(&self) -> Option<String> {
+        self.attr_opt("local,kernel")
+    }
+
+    /// Gets the timeout for I/O operations.
+    ///
+    /// This is [`NO_TIMEOUT`] if no timeout is used. The C library has no
+    /// way to query the timeout, so until it is set, this is the default
+    /// that the library uses for the backend of the context.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms())
+    }
+
     /// Sets the timeout for I/O operations
     ///
-    /// `timeout` The timeout. A value of zero specifies that no timeout
-    ///     should be used.
+    /// `timeout` The timeout. A value of zero, [`NO_TIMEOUT`], specifies
+    ///     that no timeout should be used. The library uses millisecond
+    ///     resolution, so a non-zero timeout is rounded up to at least one
+    ///     millisecond, and a very long one is limited to the largest
+    ///     value that the library accepts.
     pub fn set_timeout(&self, timeout: Duration) -> Result<()> {
-        let ms: u64 = 1000 * timeout.as_secs() + u64::from(timeout.subsec_millis());
-        self.set_timeout_ms(ms)
+        self.set_timeout_ms(timeout_to_ms(timeout))
     }
 
     /// Sets the timeout for I/O operations, in milliseconds
@@ -387,8 +672,72 @@ impl Context {
     /// `timeout` The timeout, in ms. A value of zero specifies that no
     ///     timeout should be used.
     pub fn set_timeout_ms(&self, ms: u64) -> Result<()> {
+        let _lock = self.lock_timeout();
+        self.store_timeout_ms(ms)
+    }
+
+    /// Sets the timeout in the C library, and tracks it.
+    /// The caller should hold the timeout lock.
+    fn store_timeout_ms(&self, ms: u64) -> Result<()> {
         let ret = unsafe { ffi::iio_context_set_timeout(self.inner.ctx, ms as c_uint) };
-        sys_result(ret, ())
+        sys_result(ret, ())?;
+        self.inner.timeout_ms.store(ms, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Locks the I/O timeout of the context against changes.
+    fn lock_timeout(&self) -> MutexGuard<()> {
+        self.inner.timeout_lock.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Locks the user data attached to the devices in the context.
+    pub(crate) fn lock_user_data(&self) -> MutexGuard<HashMap<*mut ffi::iio_device, UserData>> {
+        self.inner.user_data.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Gets the current timeout for I/O operations, in milliseconds.
+    pub(crate) fn timeout_ms(&self) -> u64 {
+        self.inner.timeout_ms.load(Ordering::Relaxed)
+    }
+
+    /// Runs an operation with a different I/O timeout for the context.
+    ///
+    /// The timeout is restored to its previous value after the operation
+    /// completes. The timeout is locked for the whole time, so overrides
+    /// from different threads run one after another, and the operation
+    /// must not change the timeout itself. If the operation fails, its
+    /// error is returned, even if the timeout could not be restored.
+    pub(crate) fn with_timeout<F, T>(&self, timeout: Duration, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        let _lock = self.lock_timeout();
+        let prev_ms = self.timeout_ms();
+        self.store_timeout_ms(timeout_to_ms(timeout))?;
+        let res = f();
+        let restored = self.store_timeout_ms(prev_ms);
+        let val = res?;
+        restored.map(|_| val)
+    }
+
+    /// Checks that the context can be reached, such as a connection to a
+    /// remote IIO daemon, by reading an attribute from one of its devices
+    /// with a short I/O timeout.
+    ///
+    /// This returns the time taken by the read. See [`Device::ping()`].
+    /// A context with no devices can't be checked, and always succeeds.
+    pub fn ping(&self, timeout: Duration) -> Result<Duration> {
+        match self.devices().next() {
+            Some(dev) => dev.ping(timeout),
+            None => Ok(Duration::from_secs(0)),
+        }
+    }
+
+    /// Determines if the context can be reached.
+    ///
+    /// This is a [`Context::ping()`] with a one second timeout.
+    pub fn is_alive(&self) -> bool {
+        self.ping(crate::device::ALIVE_TIMEOUT).is_ok()
     }
 
     /// Get the number of devices in the context
@@ -408,13 +757,17 @@ impl Context {
         })
     }
 
-    /// Try to find a device by name or ID
-    /// `name` The name or ID of the device to find
+    /// Try to find a device by name, ID, or label
+    /// `name` The name, ID, or label of the device to find
+    ///
+    /// The name and ID are searched first. If no device matches, this
+    /// looks for one with the label, as with
+    /// [`Context::find_device_by_label()`].
     pub fn find_device(&self, name: &str) -> Option<Device> {
-        let name = CString::new(name).unwrap();
-        let dev = unsafe { ffi::iio_context_find_device(self.inner.ctx, name.as_ptr()) };
+        let cname = CString::new(name).unwrap();
+        let dev = unsafe { ffi::iio_context_find_device(self.inner.ctx, cname.as_ptr()) };
         if dev.is_null() {
-            None
+            self.find_device_by_label(name)
         }
         else {
             Some(Device {
@@ -424,11 +777,31 @@ impl Context {
         }
     }
 
+    /// Try to find a device by its label.
+    ///
+    /// See [`Device::label()`].
+    pub fn find_device_by_label(&self, label: &str) -> Option<Device> {
+        self.devices().find(|dev| dev.label().as_deref() == Some(label))
+    }
+
     /// Gets an iterator for all the devices in the context.
     pub fn devices(&self) -> DeviceIterator {
         DeviceIterator { ctx: self, idx: 0 }
     }
 
+    /// Gets an iterator for the devices in the context that produce or
+    /// consume data.
+    ///
+    /// This skips the triggers and the hardware monitor devices.
+    pub fn data_devices(&self) -> impl Iterator<Item = Device> + '_ {
+        self.devices().filter(|dev| !dev.is_trigger() && !dev.is_hwmon())
+    }
+
+    /// Gets an iterator for the hardware monitor devices in the context.
+    pub fn hwmon_devices(&self) -> impl Iterator<Item = Device> + '_ {
+        self.devices().filter(|dev| dev.is_hwmon())
+    }
+
     /// Destroy the context
     ///
     /// This consumes the context to destroy the instance.
@@ -452,6 +825,122 @@ impl From<InnerContext> for Context {
     }
 }
 
+/// Converts an I/O timeout to milliseconds, as used by the C library.
+///
+/// A non-zero timeout is rounded up to at least one millisecond, and a
+/// very long one is limited to the largest value that the library accepts.
+fn timeout_to_ms(timeout: Duration) -> u64 {
+    let mut ms = timeout.as_secs().saturating_mul(1000) + u64::from(timeout.subsec_millis());
+    if ms == 0 && timeout != NO_TIMEOUT {
+        ms = 1;
+    }
+    ms.min(u64::from(c_uint::MAX))
+}
+
+/// Formats a host and port for the network backend, like "host:port".
+///
+/// IPv6 addresses are put in square brackets, like "[::1]:30431".
+pub(crate) fn network_host(host: &str, port: u16) -> String {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    }
+    else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// A builder to create a context with explicit options.
+///
+/// This selects the backend with a typed [`Backend`], rather than a URI
+/// string, and can set the I/O timeout as soon as the context is created.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+/// use std::time::Duration;
+///
+/// let ctx = iio::Context::builder()
+///     .backend(iio::Backend::Network("192.168.2.1"))
+///     .timeout(Duration::from_secs(2))
+///     .create()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ContextBuilder<'a> {
+    /// The backend for the context
+    backend: Backend<'a>,
+    /// The I/O timeout, if it should be set
+    timeout: Option<Duration>,
+}
+
+impl<'a> ContextBuilder<'a> {
+    /// Creates a new context builder, for the default backend.
+    pub(crate) fn new() -> Self {
+        Self {
+            backend: Backend::Default,
+            timeout: None,
+        }
+    }
+
+    /// Sets the backend for the context.
+    pub fn backend(mut self, be: Backend<'a>) -> Self {
+        self.backend = be;
+        self
+    }
+
+    /// Uses the local backend.
+    #[cfg(target_os = "linux")]
+    pub fn local(self) -> Self {
+        self.backend(Backend::Local)
+    }
+
+    /// Uses the XML backend, with the XML description in the file.
+    pub fn xml(self, path: &'a str) -> Self {
+        self.backend(Backend::Xml(path))
+    }
+
+    /// Uses the network backend, connecting to the host.
+    ///
+    /// See [`Backend::Network`].
+    pub fn network(self, host: &'a str) -> Self {
+        self.backend(Backend::Network(host))
+    }
+
+    /// Uses the USB backend, for the device.
+    ///
+    /// See [`Backend::Usb`].
+    pub fn usb(self, device: &'a str) -> Self {
+        self.backend(Backend::Usb(device))
+    }
+
+    /// Uses the serial backend, for the port and configuration.
+    ///
+    /// See [`Backend::Serial`].
+    pub fn serial(self, port: &'a str) -> Self {
+        self.backend(Backend::Serial(port))
+    }
+
+    /// Sets the timeout for I/O operations.
+    ///
+    /// A value of zero specifies that no timeout should be used. If this
+    /// isn't set, the context uses the default for its backend.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Creates the context with the configured options.
+    pub fn create(self) -> Result<Context> {
+        let ctx = Context::with_backend(self.backend)?;
+        if let Some(timeout) = self.timeout {
+            ctx.set_timeout(timeout)?;
+        }
+        Ok(ctx)
+    }
+}
+
 /// Iterator over the Devices in a Context
 #[derive(Debug)]
 pub struct DeviceIterator<'a> {
@@ -540,6 +1029,30 @@ mod tests {
         thr.join().unwrap();
     }
 
+    // The host and port are formatted for the network backend.
+    #[test]
+    fn network_host_port() {
+        assert_eq!(network_host("192.168.2.1", 30431), "192.168.2.1:30431");
+        assert_eq!(network_host("pluto.local", 1234), "pluto.local:1234");
+        assert_eq!(network_host("::1", 30431), "[::1]:30431");
+        assert_eq!(network_host("[fe80::1%eth0]", 30431), "[fe80::1%eth0]:30431");
+    }
+
+    // A copy of a context is independent of the original.
+    #[test]
+    fn try_clone_context() {
+        let ctx = Context::new().unwrap();
+        ctx.set_timeout(Duration::from_millis(1500)).unwrap();
+
+        let ctx2 = ctx.try_clone().unwrap();
+        assert!(ctx != ctx2);
+        assert_eq!(ctx2.timeout(), Duration::from_millis(1500));
+        assert_eq!(ctx2.num_devices(), ctx.num_devices());
+
+        ctx2.set_timeout(NO_TIMEOUT).unwrap();
+        assert_eq!(ctx.timeout(), Duration::from_millis(1500));
+    }
+
     // See that device iterator gets the correct number of devices.
     #[test]
     fn dev_iterator_count() {
@@ -566,4 +1079,45 @@ mod tests {
         println!("Context description: {}", desc);
         assert!(!desc.is_empty());
     }
+
+    // A context can be re-created from its XML.
+    #[test]
+    fn xml_round_trip() {
+        let ctx = Context::new().unwrap();
+        let xml_ctx = Context::from_xml(&ctx.to_xml()).unwrap();
+        assert_eq!(xml_ctx.name(), "xml");
+        assert_eq!(xml_ctx.num_devices(), ctx.num_devices());
+
+        let ids = |ctx: &Context| ctx.devices().map(|dev| dev.id()).collect::<Vec<_>>();
+        assert_eq!(ids(&xml_ctx), ids(&ctx));
+    }
+
+    // See that the timeout reads back what was set.
+    #[test]
+    fn timeout() {
+        let ctx = Context::new().unwrap();
+        ctx.set_timeout(Duration::from_millis(1500)).unwrap();
+        assert_eq!(ctx.timeout(), Duration::from_millis(1500));
+
+        ctx.set_timeout(Duration::from_micros(10)).unwrap();
+        assert_eq!(ctx.timeout(), Duration::from_millis(1));
+
+        ctx.set_timeout(NO_TIMEOUT).unwrap();
+        assert_eq!(ctx.timeout(), NO_TIMEOUT);
+    }
+
+    // A temporary timeout is restored, and the error from the operation
+    // is kept.
+    #[test]
+    fn with_timeout_restores() {
+        let ctx = Context::new().unwrap();
+        ctx.set_timeout(Duration::from_millis(1500)).unwrap();
+
+        let res: Result<()> = ctx.with_timeout(Duration::from_millis(10), || {
+            assert_eq!(ctx.timeout(), Duration::from_millis(10));
+            Err(Error::InvalidIndex)
+        });
+        assert!(matches!(res, Err(Error::InvalidIndex)));
+        assert_eq!(ctx.timeout(), Duration::from_millis(1500));
+    }
 }
@@ -0,0 +1,214 @@
+// industrial-io/src/dds.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Direct digital synthesizer (DDS) tone generators.
+//!
+//! The transmit devices of many high-speed DACs and transceivers, like
+//! the `cf-ad9361-dds-core-lpc` device for the AD936x, or the AD9144,
+//! contain DDS cores that can generate test tones without streaming any
+//! samples. Each tone is an `altvoltageN` output channel with `frequency`,
+//! `scale`, and `phase` attributes.
+//!
+//! Each I or Q output has two tones, named like "TX1_I_F1" and "TX1_I_F2",
+//! which are summed. The output comes from the DDS when it is enabled, and
+//! from DMA when a buffer is pushed to the device.
+//!
+
+use super::*;
+use nix::errno::Errno;
+
+/// A single DDS tone.
+#[derive(Debug, Clone)]
+pub struct DdsTone {
+    /// The `altvoltageN` channel for the tone
+    chan: Channel,
+}
+
+impl DdsTone {
+    /// Gets the underlying channel for the tone.
+    pub fn channel(&self) -> &Channel {
+        &self.chan
+    }
+
+    /// Gets the name of the tone, like "TX1_I_F1", if it has one.
+    pub fn name(&self) -> Option<String> {
+        self.chan.name()
+    }
+
+    /// Gets the frequency of the tone.
+    pub fn frequency(&self) -> Result<Hertz> {
+        self.chan.attr_read("frequency")
+    }
+
+    /// Sets the frequency of the tone.
+    pub fn set_frequency(&self, freq: Hertz) -> Result<()> {
+        self.chan.attr_write("frequency", freq)
+    }
+
+    /// Gets the scale of the tone, as a fraction of full scale.
+    pub fn scale(&self) -> Result<f64> {
+        self.chan.attr_read_float("scale")
+    }
+
+    /// Sets the scale of the tone, as a fraction of full scale, from 0.0
+    /// to 1.0.
+    pub fn set_scale(&self, scale: f64) -> Result<()> {
+        self.chan.attr_write_float("scale", scale)
+    }
+
+    /// Gets the phase of the tone, in degrees.
+    pub fn phase(&self) -> Result<f64> {
+        Ok(self.chan.attr_read_float("phase")? / 1000.0)
+    }
+
+    /// Sets the phase of the tone, in degrees.
+    ///
+    /// The driver holds the phase in millidegrees, from 0 to 360000.
+    pub fn set_phase(&self, deg: f64) -> Result<()> {
+        let mdeg = (deg.rem_euclid(360.0) * 1000.0).round() as i64;
+        self.chan.attr_write_int("phase", mdeg)
+    }
+
+    /// Sets the frequency, scale, and phase of the tone.
+    pub fn set(&self, freq: Hertz, scale: f64, deg: f64) -> Result<()> {
+        self.set_frequency(freq)?;
+        self.set_scale(scale)?;
+        self.set_phase(deg)
+    }
+}
+
+/// The DDS tone generators of a transmit device.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// let ctx = iio::Context::new().unwrap();
+/// let dds = iio::Dds::new(ctx.find_device("cf-ad9361-dds-core-lpc").unwrap()).unwrap();
+/// dds.set_single_tone(iio::Hertz(1.0e6), 0.5).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dds {
+    /// The transmit device
+    dev: Device,
+}
+
+impl Dds {
+    /// Creates the DDS for a transmit device.
+    ///
+    /// This fails if the device has no DDS tones.
+    pub fn new(dev: Device) -> Result<Self> {
+        let dds = Self { dev };
+        if dds.tones().next().is_none() {
+            return Err(Errno::ENOENT.into());
+        }
+        Ok(dds)
+    }
+
+    /// Gets the transmit device.
+    pub fn device(&self) -> &Device {
+        &self.dev
+    }
+
+    /// Gets an iterator for the tones of the device.
+    pub fn tones(&self) -> impl Iterator<Item = DdsTone> + '_ {
+        self.dev
+            .output_channels()
+            .filter(|chan| is_dds_channel(chan.id().as_deref(), |attr| chan.has_attr(attr)))
+            .map(|chan| DdsTone { chan })
+    }
+
+    /// Try to find a tone by its name, like "TX1_I_F1", or its channel ID.
+    pub fn find_tone(&self, name: &str) -> Option<DdsTone> {
+        self.tones().find(|tone| {
+            tone.name().as_deref() == Some(name) || tone.chan.id().as_deref() == Some(name)
+        })
+    }
+
+    /// Determines if the output comes from the DDS.
+    pub fn is_enabled(&self) -> Result<bool> {
+        let tone = self.tones().next().ok_or(Error::InvalidIndex)?;
+        tone.chan.attr_read_bool("raw")
+    }
+
+    /// Enables or disables the DDS.
+    ///
+    /// This applies to all the tones of the device. When the DDS is
+    /// disabled, the output comes from DMA, once a buffer is pushed.
+    pub fn set_enabled(&self, on: bool) -> Result<()> {
+        let tone = self.tones().next().ok_or(Error::InvalidIndex)?;
+        tone.chan.attr_write_bool("raw", on)
+    }
+
+    /// Generates a single complex tone on all the outputs, and enables
+    /// the DDS.
+    ///
+    /// This sets the first tone ("F1") of each I and Q output to the
+    /// frequency and scale, with the I tone leading the Q tone by 90
+    /// degrees, and silences the second tone ("F2"). The tones are found
+    /// by their names, so this requires a driver that names them.
+    pub fn set_single_tone(&self, freq: Hertz, scale: f64) -> Result<()> {
+        let mut n = 0;
+        for tone in self.tones() {
+            let name = tone.name().unwrap_or_default();
+            if name.ends_with("_F2") {
+                tone.set_scale(0.0)?;
+            }
+            else if name.ends_with("_I_F1") {
+                tone.set(freq, scale, 90.0)?;
+                n += 1;
+            }
+            else if name.ends_with("_Q_F1") {
+                tone.set(freq, scale, 0.0)?;
+                n += 1;
+            }
+        }
+        if n == 0 {
+            return Err(Errno::ENOENT.into());
+        }
+        self.set_enabled(true)
+    }
+}
+
+impl AsRef<Device> for Dds {
+    fn as_ref(&self) -> &Device {
+        &self.dev
+    }
+}
+
+/// Determines if a channel, with the ID and attributes, is a DDS tone.
+fn is_dds_channel<F>(id: Option<&str>, has_attr: F) -> bool
+where
+    F: Fn(&str) -> bool,
+{
+    id.map_or(false, |id| id.starts_with("altvoltage"))
+        && ["frequency", "scale", "phase"].iter().all(|attr| has_attr(attr))
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // DDS tones are "altvoltage" channels with all the tone attributes.
+    #[test]
+    fn dds_channel() {
+        let all = |_: &str| true;
+        let no_phase = |attr: &str| attr != "phase";
+
+        assert!(is_dds_channel(Some("altvoltage0"), all));
+        assert!(!is_dds_channel(Some("altvoltage0"), no_phase));
+        assert!(!is_dds_channel(Some("voltage0"), all));
+        assert!(!is_dds_channel(None, all));
+    }
+}
@@ -14,12 +14,22 @@ use super::*;
 use crate::{ffi, ATTR_BUF_SIZE};
 use nix::errno::Errno;
 use std::{
+    any::Any,
     collections::HashMap,
     ffi::CString,
+    hash::{Hash, Hasher},
     os::raw::{c_char, c_longlong, c_uint},
     ptr,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
+/// The timeout used by [`Device::is_alive()`] and [`Context::is_alive()`].
+pub(crate) const ALIVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The application data attached to a device or buffer.
+pub(crate) type UserData = Arc<dyn Any + Send + Sync>;
+
 /// An Industrial I/O Device
 ///
 /// This can not be created directly. It is obtained from a context.
@@ -33,6 +43,11 @@ pub struct Device {
 
 impl Device {
     /// Gets the context to which the device belongs
+    ///
+    /// The returned context shares the underlying C context with the one
+    /// that created the device, so settings such as the timeout apply to
+    /// both. This is used instead of `iio_device_get_context()`, which
+    /// returns an unowned pointer.
     pub fn context(&self) -> Context {
         self.ctx.clone()
     }
@@ -49,6 +64,34 @@ impl Device {
         cstring_opt(pstr)
     }
 
+    /// Gets the label of the device, if it has one.
+    ///
+    /// Labels are typically assigned to devices in the device tree, to
+    /// distinguish identical chips on a board. The v0.21 C library has no
+    /// direct support for device labels, but the kernel exposes them as
+    /// the `label` attribute of the device.
+    pub fn label(&self) -> Option<String> {
+        if !self.has_attr("label") {
+            return None;
+        }
+        self.attr_read_str("label")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Gets the XML description of the device.
+    ///
+    /// This is the `<device>` element for the device in the XML of its
+    /// context, as returned by [`Context::to_xml()`], including its channels
+    /// and attributes.
+    pub fn to_xml(&self) -> Result<String> {
+        let id = self.id().ok_or(Error::InvalidIndex)?;
+        device_xml(&self.ctx.to_xml(), &id)
+            .map(String::from)
+            .ok_or_else(|| Errno::ENOENT.into())
+    }
+
     /// Determines if the device is capable of buffered I/O.
     /// This is true if any of the channels are scan elements.
     pub fn is_buffer_capable(&self) -> bool {
@@ -66,10 +109,19 @@ impl Device {
         unsafe { ffi::iio_device_is_trigger(self.dev) }
     }
 
+    /// Determines whether the device is a hardware monitor (hwmon) device.
+    ///
+    /// The v0.21 C library has no predicate for this, but hwmon devices
+    /// are given IDs like <b><i>hwmon0</i></b>, rather than the
+    /// <b><i>iio:device0</i></b> of the IIO devices.
+    pub fn is_hwmon(&self) -> bool {
+        self.id().map_or(false, |id| id.starts_with("hwmon"))
+    }
+
     /// Associate a trigger for this device.
-    /// `trigger` The device to be used as a trigger.
-    pub fn set_trigger(&self, trigger: &Self) -> Result<()> {
-        let ret = unsafe { ffi::iio_device_set_trigger(self.dev, trigger.dev) };
+    /// `trigger` The trigger, or device to be used as a trigger.
+    pub fn set_trigger<T: AsRef<Device>>(&self, trigger: &T) -> Result<()> {
+        let ret = unsafe { ffi::iio_device_set_trigger(self.dev, trigger.as_ref().dev) };
         sys_result(ret, ())
     }
 
@@ -79,6 +131,13 @@ impl Device {
         sys_result(ret, ())
     }
 
+    /// Clears the trigger of the device.
+    ///
+    /// This is the same as [`Device::remove_trigger()`].
+    pub fn clear_trigger(&self) -> Result<()> {
+        self.remove_trigger()
+    }
+
     // ----- Attributes -----
 
     /// Determines if the device has any attributes
@@ -104,7 +163,7 @@ impl Device {
         cstring_opt(pstr)
     }
 
-    /// Determines if a buffer-specific attribute exists
+    /// Determines if a device-specific attribute exists
     pub fn has_attr(&self, name: &str) -> bool {
         let cname = cstring_or_bail_false!(name);
         let pstr = unsafe { ffi::iio_device_find_attr(self.dev, cname.as_ptr()) };
@@ -167,6 +226,16 @@ impl Device {
         sys_result(ret, val)
     }
 
+    /// Reads the values accepted by a device-specific attribute.
+    ///
+    /// This reads and parses the `<attr>_available` attribute for the
+    /// device, as reported by the driver.
+    ///
+    /// `attr` The name of the attribute, like "sampling_frequency"
+    pub fn attr_available(&self, attr: &str) -> Result<AttrChoices> {
+        self.attr_read(&format!("{}_available", attr))
+    }
+
     /// Reads all the device-specific attributes.
     /// This is especially useful when using the network backend to
     /// retrieve all the attributes with a single call.
@@ -186,6 +255,25 @@ impl Device {
         self.attr_write_str(attr, &sval)
     }
 
+    /// Writes a device-specific attribute, and reads it back.
+    ///
+    /// This returns both the requested value and the effective value that
+    /// the driver accepted, which may have been rounded or clamped.
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn attr_write_verified<T>(&self, attr: &str, val: T) -> Result<VerifiedWrite<T>>
+    where
+        T: ToAttribute + FromAttribute,
+    {
+        self.attr_write_str(attr, &val.to_attr()?)?;
+        let effective = self.attr_read(attr)?;
+        Ok(VerifiedWrite {
+            requested: val,
+            effective,
+        })
+    }
+
     /// Writes a device-specific attribute as a string
     ///
     /// `attr` The name of the attribute
@@ -267,29 +355,540 @@ impl Device {
         }
     }
 
+    /// Try to find an input channel by its name or ID.
+    ///
+    /// Unlike [`Device::find_channel()`], this returns a channel that can
+    /// only be used for input, so it can't be mistaken for an output
+    /// channel with the same ID, as on a transceiver.
+    pub fn find_input_channel(&self, name: &str) -> Option<InputChannel> {
+        self.find_channel(name, false).and_then(|chan| chan.into_input().ok())
+    }
+
+    /// Try to find an output channel by its name or ID.
+    ///
+    /// See [`Device::find_input_channel()`].
+    pub fn find_output_channel(&self, name: &str) -> Option<OutputChannel> {
+        self.find_channel(name, true).and_then(|chan| chan.into_output().ok())
+    }
+
+    /// Try to find a channel by its label.
+    ///
+    /// See [`Channel::label()`].
+    pub fn find_channel_by_label(&self, label: &str, is_output: bool) -> Option<Channel> {
+        self.channels()
+            .find(|chan| chan.is_output() == is_output && chan.label().as_deref() == Some(label))
+    }
+
     /// Gets an iterator for the channels in the device
     pub fn channels(&self) -> ChannelIterator {
         ChannelIterator { dev: self, idx: 0 }
     }
 
+    /// Gets an iterator for the input channels in the device
+    pub fn input_channels(&self) -> impl Iterator<Item = Channel> + '_ {
+        self.channels().filter(|chan| !chan.is_output())
+    }
+
+    /// Gets an iterator for the output channels in the device
+    pub fn output_channels(&self) -> impl Iterator<Item = Channel> + '_ {
+        self.channels().filter(|chan| chan.is_output())
+    }
+
+    /// Gets an iterator for the channels in the device that are scan
+    /// elements, and thus can be used with a buffer.
+    pub fn scan_elements(&self) -> impl Iterator<Item = Channel> + '_ {
+        self.channels().filter(|chan| chan.is_scan_element())
+    }
+
+    /// Gets the scan elements that are currently enabled.
+    ///
+    /// These are the channels that will be included in a buffer created
+    /// for the device. They are sorted by scan index, which is the order
+    /// in which their samples appear in the buffer.
+    pub fn enabled_channels(&self) -> Vec<Channel> {
+        let mut chans: Vec<_> = self.scan_elements().filter(|chan| chan.is_enabled()).collect();
+        chans.sort_by_key(|chan| chan.index().unwrap_or(usize::MAX));
+        chans
+    }
+
+    /// Gets the mask of the enabled channels that will be used when a
+    /// buffer is created for the device.
+    ///
+    /// As in the C library, bit `n % 32` of word `n / 32` is set if the
+    /// channel with scan index `n` is enabled.
+    pub fn enabled_mask(&self) -> Vec<u32> {
+        let mut mask = Vec::new();
+        for idx in self.enabled_channels().iter().filter_map(|chan| chan.index().ok()) {
+            let word = idx / 32;
+            if word >= mask.len() {
+                mask.resize(word + 1, 0);
+            }
+            mask[word] |= 1 << (idx % 32);
+        }
+        mask
+    }
+
+    /// Enables the channels with the specified IDs.
+    ///
+    /// Each ID is looked up as an input channel first, then as an output
+    /// channel. This fails if any of the channels is not found, or can't
+    /// be enabled, although the channels before it will have been enabled.
+    pub fn enable_channels(&self, ids: &[&str]) -> Result<()> {
+        for id in ids {
+            self.find_channel_by_id(id)?.enable()?;
+        }
+        Ok(())
+    }
+
+    /// Looks up a channel by ID, as an input channel first, then as an
+    /// output channel.
+    fn find_channel_by_id(&self, id: &str) -> Result<Channel> {
+        self.find_channel(id, false)
+            .or_else(|| self.find_channel(id, true))
+            .ok_or_else(|| Error::General(format!("Channel not found: {}", id)))
+    }
+
+    /// Enables all the scan elements of the device that match the
+    /// predicate.
+    ///
+    /// This returns the number of channels that were enabled.
+    pub fn enable_channels_where<F>(&self, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&Channel) -> bool,
+    {
+        let mut n = 0;
+        for chan in self.scan_elements().filter(|chan| f(chan)) {
+            chan.enable()?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// Enables all the scan elements of the device.
+    ///
+    /// This returns the number of channels that were enabled.
+    pub fn enable_all_scan_elements(&self) -> Result<usize> {
+        self.enable_channels_where(|_| true)
+    }
+
+    /// Gets the calibration values for all the channels of the device that
+    /// have any.
+    ///
+    /// The map is keyed by the channel ID. It can be saved, and later
+    /// restored to the device with [`Device::apply_calibration()`].
+    pub fn calibration(&self) -> Result<HashMap<String, Calibration>> {
+        let mut map = HashMap::new();
+        for chan in self.channels() {
+            let cal = chan.calibration()?;
+            if cal != Calibration::default() {
+                map.insert(chan.id().unwrap_or_default(), cal);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Applies a set of calibration values to the channels of the device.
+    ///
+    /// The map is keyed by channel ID. Each ID is looked up as an input
+    /// channel first, then as an output channel. This fails if any of the
+    /// channels is not found.
+    pub fn apply_calibration(&self, cal: &HashMap<String, Calibration>) -> Result<()> {
+        for (id, cal) in cal {
+            self.find_channel_by_id(id)?.set_calibration(cal)?;
+        }
+        Ok(())
+    }
+
+    /// Disables all the channels of the device.
+    pub fn disable_all(&self) {
+        for chan in self.channels() {
+            chan.disable();
+        }
+    }
+
+    /// Parks all the output channels of the device that can be powered
+    /// down, as with [`Channel::park()`].
+    ///
+    /// This returns the number of channels that were powered down.
+    pub fn park_outputs(&self, mode: Option<&str>) -> Result<usize> {
+        let mut n = 0;
+        for chan in self.output_channels().filter(|chan| chan.has_powerdown()) {
+            chan.park(mode)?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// Identifies the attribute that corresponds to a sysfs filename.
+    ///
+    /// This is the reverse of [`Channel::attr_filename()`]. It returns the
+    /// name of the attribute, along with the channel that it belongs to,
+    /// if the file holds a channel-specific attribute. Device attributes
+    /// are held in files with the same name as the attribute.
+    pub fn identify_filename(&self, filename: &str) -> Result<(Option<Channel>, String)> {
+        let cfilename = CString::new(filename)?;
+        let mut chan = ptr::null_mut();
+        let mut pattr = ptr::null();
+        let ret = unsafe {
+            ffi::iio_device_identify_filename(self.dev, cfilename.as_ptr(), &mut chan, &mut pattr)
+        };
+        sys_result(ret, ())?;
+
+        let attr = cstring_opt(pattr).ok_or(Error::StringConversionError)?;
+        let chan = if chan.is_null() {
+            None
+        }
+        else {
+            Some(Channel {
+                chan,
+                ctx: self.context(),
+            })
+        };
+        Ok((chan, attr))
+    }
+
+    // ----- Buffer Attributes -----
+
+    /// Determines if the device has any buffer-specific attributes
+    pub fn has_buffer_attrs(&self) -> bool {
+        unsafe { ffi::iio_device_get_buffer_attrs_count(self.dev) > 0 }
+    }
+
+    /// Gets the number of buffer-specific attributes
+    pub fn num_buffer_attrs(&self) -> usize {
+        unsafe { ffi::iio_device_get_buffer_attrs_count(self.dev) as usize }
+    }
+
+    /// Gets the name of the buffer-specific attribute at the index
+    pub fn get_buffer_attr(&self, idx: usize) -> Result<String> {
+        let pstr = unsafe { ffi::iio_device_get_buffer_attr(self.dev, idx as c_uint) };
+        cstring_opt(pstr).ok_or(Error::InvalidIndex)
+    }
+
+    /// Try to find a buffer-specific attribute by its name
+    pub fn find_buffer_attr(&self, name: &str) -> Option<String> {
+        let cname = cstring_or_bail!(name);
+        let pstr = unsafe { ffi::iio_device_find_buffer_attr(self.dev, cname.as_ptr()) };
+        cstring_opt(pstr)
+    }
+
+    /// Determines if a buffer-specific attribute exists
+    pub fn has_buffer_attr(&self, name: &str) -> bool {
+        let cname = cstring_or_bail_false!(name);
+        let pstr = unsafe { ffi::iio_device_find_buffer_attr(self.dev, cname.as_ptr()) };
+        !pstr.is_null()
+    }
+
+    /// Reads a buffer-specific attribute
+    ///
+    /// `attr` The name of the attribute
+    pub fn buffer_attr_read<T: FromAttribute>(&self, attr: &str) -> Result<T> {
+        let sval = self.buffer_attr_read_str(attr)?;
+        T::from_attr(&sval)
+    }
+
+    /// Reads a buffer-specific attribute as a string
+    ///
+    /// `attr` The name of the attribute
+    pub fn buffer_attr_read_str(&self, attr: &str) -> Result<String> {
+        let mut buf = vec![0 as c_char; ATTR_BUF_SIZE];
+        let attr = CString::new(attr)?;
+        let ret = unsafe {
+            ffi::iio_device_buffer_attr_read(self.dev, attr.as_ptr(), buf.as_mut_ptr(), buf.len())
+        };
+        sys_result(ret as i32, ())?;
+        let s = unsafe {
+            CStr::from_ptr(buf.as_ptr())
+                .to_str()
+                .map_err(|_| Error::StringConversionError)?
+        };
+        Ok(s.into())
+    }
+
+    /// Reads a buffer-specific attribute as a boolean
+    ///
+    /// `attr` The name of the attribute
+    pub fn buffer_attr_read_bool(&self, attr: &str) -> Result<bool> {
+        let mut val: bool = false;
+        let attr = CString::new(attr)?;
+        let ret =
+            unsafe { ffi::iio_device_buffer_attr_read_bool(self.dev, attr.as_ptr(), &mut val) };
+        sys_result(ret, val)
+    }
+
+    /// Reads a buffer-specific attribute as an integer (i64)
+    ///
+    /// `attr` The name of the attribute
+    pub fn buffer_attr_read_int(&self, attr: &str) -> Result<i64> {
+        let mut val: c_longlong = 0;
+        let attr = CString::new(attr)?;
+        let ret = unsafe {
+            ffi::iio_device_buffer_attr_read_longlong(self.dev, attr.as_ptr(), &mut val)
+        };
+        sys_result(ret, val as i64)
+    }
+
+    /// Reads a buffer-specific attribute as a floating-point (f64) number
+    ///
+    /// `attr` The name of the attribute
+    pub fn buffer_attr_read_float(&self, attr: &str) -> Result<f64> {
+        let mut val: f64 = 0.0;
+        let attr = CString::new(attr)?;
+        let ret = unsafe {
+            ffi::iio_device_buffer_attr_read_double(self.dev, attr.as_ptr(), &mut val)
+        };
+        sys_result(ret, val)
+    }
+
+    /// Reads all the buffer-specific attributes.
+    /// This is especially useful when using the network backend to
+    /// retrieve all the attributes with a single call.
+    pub fn buffer_attr_read_all(&self) -> Result<HashMap<String, String>> {
+        let mut map = HashMap::new();
+        let pmap = (&mut map as *mut HashMap<_, _>).cast();
+        let ret = unsafe {
+            ffi::iio_device_buffer_attr_read_all(self.dev, Some(attr_read_all_cb), pmap)
+        };
+        sys_result(ret, map)
+    }
+
+    /// Writes a buffer-specific attribute
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn buffer_attr_write<T: ToAttribute>(&self, attr: &str, val: T) -> Result<()> {
+        let sval = T::to_attr(&val)?;
+        self.buffer_attr_write_str(attr, &sval)
+    }
+
+    /// Writes a buffer-specific attribute as a string
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn buffer_attr_write_str(&self, attr: &str, val: &str) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let sval = CString::new(val)?;
+        let ret = unsafe {
+            ffi::iio_device_buffer_attr_write(self.dev, attr.as_ptr(), sval.as_ptr())
+        };
+        sys_result(ret as i32, ())
+    }
+
+    /// Writes a buffer-specific attribute as a boolean
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn buffer_attr_write_bool(&self, attr: &str, val: bool) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let ret = unsafe { ffi::iio_device_buffer_attr_write_bool(self.dev, attr.as_ptr(), val) };
+        sys_result(ret, ())
+    }
+
+    /// Writes a buffer-specific attribute as an integer (i64)
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn buffer_attr_write_int(&self, attr: &str, val: i64) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let ret =
+            unsafe { ffi::iio_device_buffer_attr_write_longlong(self.dev, attr.as_ptr(), val) };
+        sys_result(ret, ())
+    }
+
+    /// Writes a buffer-specific attribute as a floating-point (f64) number
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn buffer_attr_write_float(&self, attr: &str, val: f64) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let ret = unsafe { ffi::iio_device_buffer_attr_write_double(self.dev, attr.as_ptr(), val) };
+        sys_result(ret, ())
+    }
+
+    /// Gets an iterator for the buffer-specific attributes of the device
+    pub fn buffer_attributes(&self) -> BufferAttrIterator {
+        BufferAttrIterator { dev: self, idx: 0 }
+    }
+
     // ----- Buffer Functions -----
 
     /// Creates a buffer for the device.
     ///
     /// `sample_count` The number of samples the buffer should hold
     /// `cyclic` Whether to enable cyclic mode.
+    ///
+    /// In cyclic mode, the data in an output buffer is pushed to the
+    /// hardware only once, with a single call to [`Buffer::push()`], and
+    /// the hardware will then repeat the samples indefinitely, until the
+    /// buffer is destroyed. This is useful to generate a continuous
+    /// waveform from a DAC. Further calls to push the buffer will fail.
     pub fn create_buffer(&self, sample_count: usize, cyclic: bool) -> Result<Buffer> {
+        self.create_buffer_typed(sample_count, cyclic)
+    }
+
+    /// Creates an input buffer for the device.
+    ///
+    /// This is a buffer that can only be refilled with samples from the
+    /// hardware. The channels enabled for the buffer should be input
+    /// channels.
+    ///
+    /// `sample_count` The number of samples the buffer should hold
+    pub fn create_input_buffer(&self, sample_count: usize) -> Result<InputBuffer> {
+        self.create_buffer_typed(sample_count, false)
+    }
+
+    /// Creates an output buffer for the device.
+    ///
+    /// This is a buffer that can only be pushed to the hardware. The
+    /// channels enabled for the buffer should be output channels.
+    ///
+    /// `sample_count` The number of samples the buffer should hold
+    /// `cyclic` Whether to enable cyclic mode.
+    pub fn create_output_buffer(&self, sample_count: usize, cyclic: bool) -> Result<OutputBuffer> {
+        self.create_buffer_typed(sample_count, cyclic)
+    }
+
+    /// Creates a buffer for the device with the requested direction.
+    pub(crate) fn create_buffer_typed<D>(
+        &self,
+        sample_count: usize,
+        cyclic: bool,
+    ) -> Result<Buffer<D>> {
         let buf = unsafe { ffi::iio_device_create_buffer(self.dev, sample_count, cyclic) };
         if buf.is_null() {
             return Err(Errno::last().into());
         }
-        Ok(Buffer {
-            buf,
-            cap: sample_count,
-            dev: self.clone(),
+        Ok(Buffer::new(buf, sample_count, cyclic, self.clone()))
+    }
+
+    /// Sets the number of kernel buffers for the device.
+    ///
+    /// This sets the depth of the queue of buffers in the kernel, which can
+    /// help prevent overruns at high sample rates. It must be called before
+    /// a buffer is created for the device to take effect.
+    pub fn set_num_kernel_buffers(&self, n: u32) -> Result<()> {
+        let ret = unsafe { ffi::iio_device_set_kernel_buffers_count(self.dev, n as c_uint) };
+        sys_result(ret, ())
+    }
+
+    /// Sets the watermark for buffers created on the device.
+    ///
+    /// This is the number of samples the kernel waits for before it marks
+    /// the buffer as readable for `poll()`, such as on the file descriptor
+    /// from [`Buffer::poll_fd()`]. It doesn't make a refill return sooner,
+    /// since the C library keeps reading until the whole buffer is filled.
+    /// For lower latency, create the buffer with a smaller sample count.
+    ///
+    /// This is written to the `watermark` buffer attribute of the device,
+    /// and must be set before the buffer is created, as the kernel won't
+    /// change it while a buffer is enabled.
+    pub fn set_buffer_watermark(&self, n: usize) -> Result<()> {
+        self.buffer_attr_write_int("watermark", n as i64)
+    }
+
+    /// Gets a builder to configure and create a buffer for the device.
+    ///
+    /// This allows setting options, such as the number of kernel buffers
+    /// and the buffer watermark, before the buffer is created.
+    pub fn buffer_builder(&self) -> BufferBuilder {
+        BufferBuilder::new(self)
+    }
+
+    /// Creates a pool of input buffers that are refilled on a worker thread.
+    ///
+    /// This hides the latency of refilling the buffer. See [`BufferPool`]
+    /// for details.
+    ///
+    /// `sample_count` The number of samples each buffer should hold
+    /// `depth` The number of blocks in the pool, normally two or more.
+    pub fn create_buffer_pool(&self, sample_count: usize, depth: usize) -> Result<BufferPool> {
+        self.buffer_builder().sample_count(sample_count).create_pool(depth)
+    }
+
+    // ----- Health Check -----
+
+    /// Checks that the device can be reached, by reading one of its
+    /// attributes with a short I/O timeout.
+    ///
+    /// This returns the time taken by the read. The timeout applies to
+    /// the whole context while the check is in progress, including any I/O
+    /// on it from other threads. A device that has no attributes can't be
+    /// checked, and always succeeds.
+    pub fn ping(&self, timeout: Duration) -> Result<Duration> {
+        self.context().with_timeout(timeout, || {
+            let start = Instant::now();
+            self.read_any_attr()?;
+            Ok(start.elapsed())
         })
     }
 
+    /// Determines if the device can be reached.
+    ///
+    /// This is a [`Device::ping()`] with a one second timeout.
+    pub fn is_alive(&self) -> bool {
+        self.ping(ALIVE_TIMEOUT).is_ok()
+    }
+
+    /// Reads the first device attribute, or if there are none, the first
+    /// attribute of a channel.
+    fn read_any_attr(&self) -> Result<()> {
+        if let Ok(attr) = self.get_attr(0) {
+            return self.attr_read_str(&attr).map(|_| ());
+        }
+        for chan in self.channels() {
+            if let Ok(attr) = chan.get_attr(0) {
+                return chan.attr_read_str(&attr).map(|_| ());
+            }
+        }
+        Ok(())
+    }
+
+    // ----- User Data -----
+
+    /// Attaches application data to the device.
+    ///
+    /// The data is shared by all the [`Device`] objects that refer to the
+    /// same device, and can be retrieved from any of them with
+    /// [`Device::user_data()`]. Any previous data is dropped. The data is
+    /// held until it is replaced, cleared, or the context is destroyed.
+    ///
+    /// The data is kept by this crate, apart from the data pointer of the
+    /// C device, which is left for use by any other C code.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use industrial_io as iio;
+    ///
+    /// let ctx = iio::Context::new().unwrap();
+    /// let dev = ctx.get_device(0).unwrap();
+    /// dev.set_user_data(String::from("left sensor"));
+    ///
+    /// let dev = ctx.get_device(0).unwrap();
+    /// assert_eq!(*dev.user_data::<String>().unwrap(), "left sensor");
+    /// ```
+    pub fn set_user_data<T: Any + Send + Sync>(&self, data: T) {
+        let data: UserData = Arc::new(data);
+        let prev = self.ctx.lock_user_data().insert(self.dev, data);
+        // Drop any previous data after releasing the lock
+        drop(prev);
+    }
+
+    /// Gets the application data attached to the device.
+    ///
+    /// This returns `None` if there is no data, or if it is not of type
+    /// `T`.
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let data = self.ctx.lock_user_data().get(&self.dev).cloned()?;
+        data.downcast().ok()
+    }
+
+    /// Removes and drops the application data attached to the device.
+    pub fn clear_user_data(&self) {
+        let prev = self.ctx.lock_user_data().remove(&self.dev);
+        drop(prev);
+    }
+
     // ----- Low-level & Debug functions -----
 
     /// Gets the current sample size, in bytes.
@@ -312,6 +911,197 @@ impl Device {
         let ret = unsafe { ffi::iio_device_reg_write(self.dev, addr, val) };
         sys_result(ret as i32, ())
     }
+
+    /// Determines if the device has any debug attributes
+    pub fn has_debug_attrs(&self) -> bool {
+        unsafe { ffi::iio_device_get_debug_attrs_count(self.dev) > 0 }
+    }
+
+    /// Gets the number of debug attributes
+    pub fn num_debug_attrs(&self) -> usize {
+        unsafe { ffi::iio_device_get_debug_attrs_count(self.dev) as usize }
+    }
+
+    /// Gets the name of the debug attribute at the index
+    pub fn get_debug_attr(&self, idx: usize) -> Result<String> {
+        let pstr = unsafe { ffi::iio_device_get_debug_attr(self.dev, idx as c_uint) };
+        cstring_opt(pstr).ok_or(Error::InvalidIndex)
+    }
+
+    /// Try to find a debug attribute by its name
+    pub fn find_debug_attr(&self, name: &str) -> Option<String> {
+        let cname = cstring_or_bail!(name);
+        let pstr = unsafe { ffi::iio_device_find_debug_attr(self.dev, cname.as_ptr()) };
+        cstring_opt(pstr)
+    }
+
+    /// Determines if a debug attribute exists
+    pub fn has_debug_attr(&self, name: &str) -> bool {
+        let cname = cstring_or_bail_false!(name);
+        let pstr = unsafe { ffi::iio_device_find_debug_attr(self.dev, cname.as_ptr()) };
+        !pstr.is_null()
+    }
+
+    /// Reads a debug attribute
+    ///
+    /// `attr` The name of the attribute
+    pub fn debug_attr_read<T: FromAttribute>(&self, attr: &str) -> Result<T> {
+        let sval = self.debug_attr_read_str(attr)?;
+        T::from_attr(&sval)
+    }
+
+    /// Reads a debug attribute as a string
+    ///
+    /// `attr` The name of the attribute
+    pub fn debug_attr_read_str(&self, attr: &str) -> Result<String> {
+        let mut buf = vec![0 as c_char; ATTR_BUF_SIZE];
+        let attr = CString::new(attr)?;
+        let ret = unsafe {
+            ffi::iio_device_debug_attr_read(self.dev, attr.as_ptr(), buf.as_mut_ptr(), buf.len())
+        };
+        sys_result(ret as i32, ())?;
+        let s = unsafe {
+            CStr::from_ptr(buf.as_ptr())
+                .to_str()
+                .map_err(|_| Error::StringConversionError)?
+        };
+        Ok(s.into())
+    }
+
+    /// Reads a debug attribute as a boolean
+    ///
+    /// `attr` The name of the attribute
+    pub fn debug_attr_read_bool(&self, attr: &str) -> Result<bool> {
+        let mut val: bool = false;
+        let attr = CString::new(attr)?;
+        let ret =
+            unsafe { ffi::iio_device_debug_attr_read_bool(self.dev, attr.as_ptr(), &mut val) };
+        sys_result(ret, val)
+    }
+
+    /// Reads a debug attribute as an integer (i64)
+    ///
+    /// `attr` The name of the attribute
+    pub fn debug_attr_read_int(&self, attr: &str) -> Result<i64> {
+        let mut val: c_longlong = 0;
+        let attr = CString::new(attr)?;
+        let ret = unsafe {
+            ffi::iio_device_debug_attr_read_longlong(self.dev, attr.as_ptr(), &mut val)
+        };
+        sys_result(ret, val as i64)
+    }
+
+    /// Reads a debug attribute as a floating-point (f64) number
+    ///
+    /// `attr` The name of the attribute
+    pub fn debug_attr_read_float(&self, attr: &str) -> Result<f64> {
+        let mut val: f64 = 0.0;
+        let attr = CString::new(attr)?;
+        let ret = unsafe {
+            ffi::iio_device_debug_attr_read_double(self.dev, attr.as_ptr(), &mut val)
+        };
+        sys_result(ret, val)
+    }
+
+    /// Reads all the debug attributes.
+    /// This is especially useful when using the network backend to
+    /// retrieve all the attributes with a single call.
+    pub fn debug_attr_read_all(&self) -> Result<HashMap<String, String>> {
+        let mut map = HashMap::new();
+        let pmap = (&mut map as *mut HashMap<_, _>).cast();
+        let ret = unsafe {
+            ffi::iio_device_debug_attr_read_all(self.dev, Some(attr_read_all_cb), pmap)
+        };
+        sys_result(ret, map)
+    }
+
+    /// Writes a debug attribute
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn debug_attr_write<T: ToAttribute>(&self, attr: &str, val: T) -> Result<()> {
+        let sval = T::to_attr(&val)?;
+        self.debug_attr_write_str(attr, &sval)
+    }
+
+    /// Writes a debug attribute as a string
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn debug_attr_write_str(&self, attr: &str, val: &str) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let sval = CString::new(val)?;
+        let ret = unsafe {
+            ffi::iio_device_debug_attr_write(self.dev, attr.as_ptr(), sval.as_ptr())
+        };
+        sys_result(ret as i32, ())
+    }
+
+    /// Writes a debug attribute as a boolean
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn debug_attr_write_bool(&self, attr: &str, val: bool) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let ret = unsafe { ffi::iio_device_debug_attr_write_bool(self.dev, attr.as_ptr(), val) };
+        sys_result(ret, ())
+    }
+
+    /// Writes a debug attribute as an integer (i64)
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn debug_attr_write_int(&self, attr: &str, val: i64) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let ret =
+            unsafe { ffi::iio_device_debug_attr_write_longlong(self.dev, attr.as_ptr(), val) };
+        sys_result(ret, ())
+    }
+
+    /// Writes a debug attribute as a floating-point (f64) number
+    ///
+    /// `attr` The name of the attribute
+    /// `val` The value to write
+    pub fn debug_attr_write_float(&self, attr: &str, val: f64) -> Result<()> {
+        let attr = CString::new(attr)?;
+        let ret = unsafe { ffi::iio_device_debug_attr_write_double(self.dev, attr.as_ptr(), val) };
+        sys_result(ret, ())
+    }
+
+    /// Gets an iterator for the debug attributes of the device
+    pub fn debug_attributes(&self) -> DebugAttrIterator {
+        DebugAttrIterator { dev: self, idx: 0 }
+    }
+
+    // ----- Raw handles -----
+
+    /// Creates a device from a raw C device pointer.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be a valid, non-NULL, device that belongs to the
+    /// specified context.
+    pub unsafe fn from_raw(ctx: &Context, dev: *mut ffi::iio_device) -> Self {
+        Self {
+            dev,
+            ctx: ctx.clone(),
+        }
+    }
+
+    /// Gets the raw pointer to the underlying C device.
+    ///
+    /// This can be passed to other C libraries that work with IIO devices,
+    /// such as _libad9361_. The device is owned by its context, and the
+    /// pointer is only valid while a reference to the context is alive.
+    pub fn as_raw(&self) -> *mut ffi::iio_device {
+        self.dev
+    }
+}
+
+impl AsRef<Device> for Device {
+    fn as_ref(&self) -> &Device {
+        self
+    }
 }
 
 // The Device can be sent to another thread.
@@ -325,6 +1115,23 @@ impl PartialEq for Device {
     }
 }
 
+impl Eq for Device {}
+
+impl Hash for Device {
+    /// Hashes the identity of the underlying object, to be consistent with
+    /// the equality comparison.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.dev.hash(state)
+    }
+}
+
+/// Finds the `<device>` element with the ID in the XML for a context.
+fn device_xml<'a>(xml: &'a str, id: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<device id=\"{}\"", id))?;
+    let len = xml[start..].find("</device>")? + "</device>".len();
+    Some(&xml[start..start + len])
+}
+
 /// Iterator over the Channels in a Device
 #[derive(Debug)]
 pub struct ChannelIterator<'a> {
@@ -348,6 +1155,52 @@ impl<'a> Iterator for ChannelIterator<'a> {
     }
 }
 
+/// Iterator over the buffer-specific attributes in a Device
+#[derive(Debug)]
+pub struct BufferAttrIterator<'a> {
+    /// Reference to the Device that we're scanning for attributes
+    dev: &'a Device,
+    /// Index for the next buffer attribute from the iterator
+    idx: usize,
+}
+
+impl<'a> Iterator for BufferAttrIterator<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.dev.get_buffer_attr(self.idx) {
+            Ok(name) => {
+                self.idx += 1;
+                Some(name)
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Iterator over the debug attributes in a Device
+#[derive(Debug)]
+pub struct DebugAttrIterator<'a> {
+    /// Reference to the Device that we're scanning for attributes
+    dev: &'a Device,
+    /// Index for the next debug attribute from the iterator
+    idx: usize,
+}
+
+impl<'a> Iterator for DebugAttrIterator<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.dev.get_debug_attr(self.idx) {
+            Ok(name) => {
+                self.idx += 1;
+                Some(name)
+            }
+            Err(_) => None,
+        }
+    }
+}
+
 /// Iterator over the attributes in a Device
 #[derive(Debug)]
 pub struct AttrIterator<'a> {
@@ -406,6 +1259,44 @@ mod tests {
         assert!(dev.attributes().count() == n);
     }
 
+    // The XML for a device is extracted from the context XML.
+    #[test]
+    fn device_xml_element() {
+        let xml = concat!(
+            "<context name=\"local\">",
+            "<device id=\"iio:device0\" name=\"ads1015\"><attribute name=\"a\" /></device>",
+            "<device id=\"iio:device1\" name=\"mcp4725\"></device>",
+            "</context>"
+        );
+        assert_eq!(
+            device_xml(xml, "iio:device1"),
+            Some("<device id=\"iio:device1\" name=\"mcp4725\"></device>")
+        );
+        assert!(device_xml(xml, "iio:device0").unwrap().ends_with("/></device>"));
+        assert_eq!(device_xml(xml, "iio:device2"), None);
+    }
+
+    // User data is kept apart from the data pointer of the C device,
+    // which might belong to other C code.
+    #[test]
+    fn user_data_leaves_c_data() {
+        let ctx = Context::new().unwrap();
+        let dev = ctx.get_device(0).unwrap();
+
+        let mut foreign = 42u32;
+        let pforeign = (&mut foreign as *mut u32).cast();
+        unsafe { ffi::iio_device_set_data(dev.dev, pforeign) };
+
+        dev.set_user_data(String::from("sensor"));
+        assert_eq!(*dev.user_data::<String>().unwrap(), "sensor");
+        assert!(dev.user_data::<u32>().is_none());
+        dev.clear_user_data();
+        assert!(dev.user_data::<String>().is_none());
+
+        assert_eq!(unsafe { ffi::iio_device_get_data(dev.dev) }, pforeign);
+        unsafe { ffi::iio_device_set_data(dev.dev, ptr::null_mut()) };
+    }
+
     // Just the fact that this compiles is probably sufficient.
     #[test]
     fn test_device_send() {
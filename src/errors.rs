@@ -33,12 +33,49 @@ pub enum Error {
     /// The wrong data type used in an operation
     #[error("Wrong data type")]
     WrongDataType,
+    /// The size of a sample type did not match the size of the samples
+    /// for a channel. This gives the size of the type and the size of the
+    /// channel's samples, in bytes.
+    #[error("Wrong sample size: type is {0} bytes, but channel sample is {1} bytes")]
+    WrongSampleSize(usize, usize),
+    /// An operation required a channel that has not been enabled.
+    #[error("Channel not enabled")]
+    ChannelNotEnabled,
     /// The size of a data or return value was different than expected.
     #[error("Bad return size")]
     BadReturnSize,
+    /// A value is not one of those accepted by an attribute, as given by
+    /// its `*_available` attribute.
+    #[error("Value not available for the attribute")]
+    ValueNotAvailable,
+    /// An operation required a device that is a trigger.
+    #[error("Device is not a trigger")]
+    NotTrigger,
+    /// An operation required a hardware monitor (hwmon) device.
+    #[error("Device is not a hardware monitor")]
+    NotHwmon,
+    /// A channel that is not a scan element can not be enabled.
+    #[error("Channel is not a scan element")]
+    NotScanElement,
+    /// An operation was attempted on a channel of the wrong direction.
+    #[error("Wrong channel direction")]
+    WrongDirection,
+    /// The samples for a channel are interleaved with other channels
+    /// in the buffer, and can not be accessed as a contiguous slice.
+    #[error("Channel data is not contiguous")]
+    NotContiguous,
     /// A device or channel index did not find a requested object
     #[error("Invalid index")]
     InvalidIndex,
+    /// Data could not be arranged into an array of the requested shape.
+    #[cfg(feature = "ndarray")]
+    #[error("{0}")]
+    Shape(#[from] ndarray::ShapeError),
+    /// Buffer memory could not be cast to a plain-old-data type, due to
+    /// its size or alignment.
+    #[cfg(feature = "bytemuck")]
+    #[error("Pod cast error: {0:?}")]
+    PodCast(bytemuck::PodCastError),
     /// A generic error with a string explaination
     #[error("{0}")]
     General(String),
@@ -0,0 +1,210 @@
+// industrial-io/src/events.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Configuration of the IIO events for channels.
+//!
+//! Many sensors can generate events when the value of a channel crosses a
+//! threshold, or changes too quickly. These are configured through the
+//! attributes in the `events/` sysfs directory of the device, such as
+//! `in_voltage0_thresh_rising_value` and `in_voltage0_thresh_rising_en`.
+//!
+//! The v0.21 C library does not expose the event attributes, so they are
+//! accessed directly through sysfs. This only works for devices in a
+//! context using the local backend.
+//!
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use super::*;
+use nix::errno::Errno;
+
+/// The root of the sysfs directories for the IIO devices.
+const SYSFS_IIO_PATH: &str = "/sys/bus/iio/devices";
+
+/// The type and direction of an event.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    ThresholdRising,
+    ThresholdFalling,
+    ThresholdEither,
+    MagnitudeRising,
+    MagnitudeFalling,
+    MagnitudeEither,
+    RocRising,
+    RocFalling,
+    RocEither,
+}
+
+impl EventType {
+    /// Gets the name used for the event in the sysfs attributes, like
+    /// "thresh_rising".
+    pub fn name(&self) -> &'static str {
+        use EventType::*;
+        match *self {
+            ThresholdRising => "thresh_rising",
+            ThresholdFalling => "thresh_falling",
+            ThresholdEither => "thresh_either",
+            MagnitudeRising => "mag_rising",
+            MagnitudeFalling => "mag_falling",
+            MagnitudeEither => "mag_either",
+            RocRising => "roc_rising",
+            RocFalling => "roc_falling",
+            RocEither => "roc_either",
+        }
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The configuration of one type of event for a channel.
+///
+/// This is obtained from [`Channel::event_config()`]. Each setting is held
+/// in a sysfs attribute that is either specific to the channel, or shared
+/// by all the channels of the same type. The channel-specific attribute
+/// is used if it exists.
+#[derive(Debug, Clone)]
+pub struct EventConfig {
+    /// The `events/` directory of the device
+    dir: PathBuf,
+    /// The attribute prefixes to search, most specific first
+    prefixes: Vec<String>,
+}
+
+impl EventConfig {
+    /// Gets the path to the file for an event setting.
+    fn path(&self, setting: &str) -> Result<PathBuf> {
+        self.prefixes
+            .iter()
+            .map(|prefix| self.dir.join(format!("{}{}", prefix, setting)))
+            .find(|path| path.exists())
+            .ok_or_else(|| Errno::ENOENT.into())
+    }
+
+    /// Reads an event setting.
+    fn read(&self, setting: &str) -> Result<String> {
+        let s = fs::read_to_string(self.path(setting)?)?;
+        Ok(s.trim().to_string())
+    }
+
+    /// Writes an event setting.
+    fn write(&self, setting: &str, val: &str) -> Result<()> {
+        fs::write(self.path(setting)?, val)?;
+        Ok(())
+    }
+
+    /// Determines if the channel supports the event.
+    pub fn is_supported(&self) -> bool {
+        self.path("en").is_ok()
+    }
+
+    /// Determines if the event is enabled.
+    pub fn is_enabled(&self) -> Result<bool> {
+        Ok(self.read("en")? != "0")
+    }
+
+    /// Enables the event.
+    pub fn enable(&self) -> Result<()> {
+        self.write("en", "1")
+    }
+
+    /// Disables the event.
+    pub fn disable(&self) -> Result<()> {
+        self.write("en", "0")
+    }
+
+    /// Gets the value at which the event fires, in units of the raw
+    /// samples.
+    pub fn value(&self) -> Result<f64> {
+        self.read("value")?.parse().map_err(|_| Error::StringConversionError)
+    }
+
+    /// Sets the value at which the event fires, in units of the raw
+    /// samples.
+    pub fn set_value(&self, val: f64) -> Result<&Self> {
+        self.write("value", &val.to_string())?;
+        Ok(self)
+    }
+
+    /// Gets the hysteresis of the event, in units of the raw samples.
+    pub fn hysteresis(&self) -> Result<f64> {
+        self.read("hysteresis")?.parse().map_err(|_| Error::StringConversionError)
+    }
+
+    /// Sets the hysteresis of the event, in units of the raw samples.
+    pub fn set_hysteresis(&self, val: f64) -> Result<&Self> {
+        self.write("hysteresis", &val.to_string())?;
+        Ok(self)
+    }
+
+    /// Gets the time, in seconds, that the condition must hold before the
+    /// event fires.
+    pub fn period(&self) -> Result<f64> {
+        self.read("period")?.parse().map_err(|_| Error::StringConversionError)
+    }
+
+    /// Sets the time, in seconds, that the condition must hold before the
+    /// event fires.
+    pub fn set_period(&self, secs: f64) -> Result<&Self> {
+        self.write("period", &secs.to_string())?;
+        Ok(self)
+    }
+}
+
+impl Channel {
+    /// Gets the configuration of an event for the channel.
+    ///
+    /// This fails if the channel is not in a context using the local
+    /// backend, or if the device has no `events/` directory. Use
+    /// [`EventConfig::is_supported()`] to determine if the channel can
+    /// generate the event.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use industrial_io as iio;
+    ///
+    /// let ctx = iio::Context::new().unwrap();
+    /// let dev = ctx.find_device("ads1015").unwrap();
+    /// let chan = dev.find_channel("voltage0", false).unwrap();
+    ///
+    /// chan.event_config(iio::EventType::ThresholdRising)
+    ///     .unwrap()
+    ///     .set_value(1200.0)
+    ///     .unwrap()
+    ///     .enable()
+    ///     .unwrap();
+    /// ```
+    pub fn event_config(&self, typ: EventType) -> Result<EventConfig> {
+        if self.ctx.name() != "local" {
+            return Err(Error::General("Events require the local backend".into()));
+        }
+
+        let dev_id = self.device().id().ok_or(Error::InvalidIndex)?;
+        let dir = Path::new(SYSFS_IIO_PATH).join(dev_id).join("events");
+        if !dir.is_dir() {
+            return Err(Errno::ENOENT.into());
+        }
+
+        let dir_name = if self.is_output() { "out" } else { "in" };
+        let chan_id = self.id().unwrap_or_default();
+        let prefixes = vec![
+            format!("{}_{}_{}_", dir_name, chan_id, typ),
+            format!("{}_{}_{}_", dir_name, self.channel_type(), typ),
+        ];
+        Ok(EventConfig { dir, prefixes })
+    }
+}
@@ -0,0 +1,318 @@
+// industrial-io/src/hotplug.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Detection of devices being added to or removed from a system.
+//!
+//! A [`HotplugWatcher`] keeps a snapshot of the devices in a context, and
+//! reports the ones that appear or disappear, such as when a USB data
+//! acquisition module is plugged in or unplugged. A context only holds the
+//! devices that existed when it was created, so each scan creates a new
+//! context.
+//!
+//! For the local backend, the watcher listens on a netlink socket for the
+//! kernel uevents of the IIO subsystem, and scans as soon as one arrives.
+//! Other backends are scanned periodically.
+//!
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    os::{raw::c_int, unix::io::RawFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::*;
+use nix::{
+    errno::Errno,
+    libc,
+    poll::{poll, PollFd, PollFlags},
+    sys::socket::{bind, recv, MsgFlags, SockAddr},
+    unistd,
+};
+
+/// The longest time a watcher thread waits on the uevent socket before
+/// checking if it should stop.
+const UEVENT_POLL_MS: u64 = 100;
+
+/// The netlink multicast group for kernel uevents.
+const UEVENT_GROUP: u32 = 1;
+
+/// The identity of a device that was added or removed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HotplugDevice {
+    /// The ID of the device (e.g. <b><i>iio:device0</i></b>)
+    pub id: String,
+    /// The name of the device, if it has one
+    pub name: Option<String>,
+}
+
+impl HotplugDevice {
+    /// Gets the identity of a device.
+    fn new(dev: &Device) -> Self {
+        Self {
+            id: dev.id().unwrap_or_default(),
+            name: dev.name(),
+        }
+    }
+}
+
+/// A change to the devices in a system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotplugEvent {
+    /// A device was added
+    Added(HotplugDevice),
+    /// A device was removed
+    Removed(HotplugDevice),
+}
+
+/// A function to create a new context for each scan.
+type ContextFactory = Box<dyn Fn() -> Result<Context> + Send>;
+
+/// A watcher for devices that are added to or removed from a system.
+///
+/// The first scan reads the initial set of devices. Each scan after that
+/// reports the devices that were added or removed since the previous one.
+/// A device whose name changes is reported as removed, then added.
+pub struct HotplugWatcher {
+    /// Creates the contexts to scan
+    new_context: ContextFactory,
+    /// The socket for kernel uevents, for the local backend
+    uevents: Option<UeventSocket>,
+    /// The longest time between scans, when run on a thread
+    period: Duration,
+    /// The devices found by the last scan
+    devices: Option<BTreeSet<HotplugDevice>>,
+}
+
+impl HotplugWatcher {
+    /// Creates a watcher that scans contexts created by the function.
+    ///
+    /// When run on a thread, the contexts are scanned at the specified
+    /// period.
+    pub fn new<F>(period: Duration, f: F) -> Self
+    where
+        F: Fn() -> Result<Context> + Send + 'static,
+    {
+        Self {
+            new_context: Box::new(f),
+            uevents: None,
+            period,
+            devices: None,
+        }
+    }
+
+    /// Creates a watcher for the devices on the local system.
+    ///
+    /// When run on a thread, this scans whenever the kernel reports a
+    /// change to the IIO devices, and otherwise at the specified period.
+    /// If the uevent socket can't be opened, it only scans periodically.
+    pub fn local(period: Duration) -> Self {
+        let mut watcher = Self::new(period, || Context::with_backend(Backend::Local));
+        watcher.uevents = UeventSocket::open().ok();
+        watcher
+    }
+
+    /// Creates a watcher for the devices in the context specified by the
+    /// `uri`.
+    pub fn from_uri(uri: &str, period: Duration) -> Self {
+        let uri = uri.to_string();
+        Self::new(period, move || Context::from_uri(&uri))
+    }
+
+    /// Gets the devices found by the last scan, if any.
+    pub fn devices(&self) -> Option<&BTreeSet<HotplugDevice>> {
+        self.devices.as_ref()
+    }
+
+    /// Scans the devices, and returns the ones that were added or removed
+    /// since the last scan.
+    ///
+    /// This fails if the context can't be created, in which case the
+    /// devices from the previous scan are kept.
+    pub fn scan(&mut self) -> Result<Vec<HotplugEvent>> {
+        let ctx = (self.new_context)()?;
+        let devices: BTreeSet<_> = ctx.devices().map(|dev| HotplugDevice::new(&dev)).collect();
+
+        let mut events = Vec::new();
+        if let Some(prev) = self.devices.as_ref() {
+            events.extend(prev.difference(&devices).cloned().map(HotplugEvent::Removed));
+            events.extend(devices.difference(prev).cloned().map(HotplugEvent::Added));
+        }
+        self.devices = Some(devices);
+        Ok(events)
+    }
+
+    /// Runs the watcher on a background thread, sending the events to the
+    /// returned receiver.
+    ///
+    /// The thread stops when the handle is dropped, or when the receiver
+    /// is dropped.
+    pub fn spawn(self) -> (WatchHandle, mpsc::Receiver<HotplugEvent>) {
+        let (tx, rx) = mpsc::channel();
+        let handle = self.spawn_inner(move |event| tx.send(event).is_ok());
+        (handle, rx)
+    }
+
+    /// Runs the watcher on a background thread, calling the callback for
+    /// each event.
+    ///
+    /// The thread stops when the handle is dropped.
+    pub fn spawn_with_callback<F>(self, mut f: F) -> WatchHandle
+    where
+        F: FnMut(HotplugEvent) + Send + 'static,
+    {
+        self.spawn_inner(move |event| {
+            f(event);
+            true
+        })
+    }
+
+    /// Runs the watcher on a thread, passing each event to the function,
+    /// until it returns `false` or the handle signals a stop.
+    fn spawn_inner<F>(mut self, mut f: F) -> WatchHandle
+    where
+        F: FnMut(HotplugEvent) -> bool + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thr_stop = Arc::clone(&stop);
+
+        let thr = thread::spawn(move || {
+            let _ = self.scan();
+            while !thr_stop.load(Ordering::SeqCst) {
+                self.wait(&thr_stop);
+                if thr_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                for event in self.scan().unwrap_or_default() {
+                    if !f(event) {
+                        return;
+                    }
+                }
+            }
+        });
+
+        WatchHandle::new(stop, thr)
+    }
+
+    /// Waits until the next scan is due, a uevent arrives from the IIO
+    /// subsystem, or the thread is signaled to stop.
+    fn wait(&self, stop: &AtomicBool) {
+        let deadline = Instant::now() + self.period;
+        while !stop.load(Ordering::SeqCst) {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let remaining = deadline - now;
+            match self.uevents.as_ref() {
+                Some(sock) => {
+                    if sock.wait(remaining.min(Duration::from_millis(UEVENT_POLL_MS))) {
+                        break;
+                    }
+                }
+                None => thread::park_timeout(remaining),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for HotplugWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HotplugWatcher")
+            .field("uevents", &self.uevents)
+            .field("period", &self.period)
+            .field("devices", &self.devices)
+            .finish()
+    }
+}
+
+/// A netlink socket that receives the kernel uevents.
+#[derive(Debug)]
+struct UeventSocket {
+    /// The socket file descriptor
+    fd: RawFd,
+}
+
+impl UeventSocket {
+    /// Opens a socket for the kernel uevents.
+    fn open() -> Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                libc::NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd < 0 {
+            return Err(Errno::last().into());
+        }
+        let sock = Self { fd };
+        bind(sock.fd, &SockAddr::new_netlink(0, UEVENT_GROUP))?;
+        Ok(sock)
+    }
+
+    /// Waits for uevents, up to the timeout, and reads all of those that
+    /// are available.
+    ///
+    /// This returns `true` if any of them were from the IIO subsystem.
+    fn wait(&self, timeout: Duration) -> bool {
+        let mut fds = [PollFd::new(self.fd, PollFlags::POLLIN)];
+        match poll(&mut fds, timeout.as_millis() as c_int) {
+            Ok(n) if n > 0 => (),
+            _ => return false,
+        }
+
+        let mut buf = [0u8; 8192];
+        let mut found = false;
+        while let Ok(n) = recv(self.fd, &mut buf, MsgFlags::MSG_DONTWAIT) {
+            if n == 0 {
+                break;
+            }
+            found |= is_iio_uevent(&buf[..n]);
+        }
+        found
+    }
+}
+
+impl Drop for UeventSocket {
+    fn drop(&mut self) {
+        let _ = unistd::close(self.fd);
+    }
+}
+
+/// Determines if a kernel uevent message is from the IIO subsystem.
+///
+/// The message is a header followed by NUL-terminated "KEY=value" fields.
+fn is_iio_uevent(msg: &[u8]) -> bool {
+    msg.split(|&b| b == 0).any(|field| field == b"SUBSYSTEM=iio")
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only uevents with the IIO subsystem are recognized.
+    #[test]
+    fn iio_uevent() {
+        let msg = b"add@/devices/iio:device1\0ACTION=add\0SUBSYSTEM=iio\0SEQNUM=1\0";
+        assert!(is_iio_uevent(msg));
+
+        let msg = b"add@/devices/usb1/1-1\0ACTION=add\0SUBSYSTEM=usb\0SEQNUM=2\0";
+        assert!(!is_iio_uevent(msg));
+    }
+}
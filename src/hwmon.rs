@@ -0,0 +1,281 @@
+// industrial-io/src/hwmon.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Hardware monitor (hwmon) devices.
+//!
+//! Contexts may include the hardware monitors of the system, such as for
+//! CPU temperatures, fan speeds, and supply voltages. These are exposed
+//! as devices where [`Device::is_hwmon()`] is true, with channels like
+//! "temp1" and "fan1", that have attributes like "input" and "max".
+//!
+//! A [`Hwmon`] wraps one of these devices, and gives typed access to its
+//! sensors. The values are reported in the units of the hwmon sysfs ABI,
+//! except for power, which is converted from microwatts to milliwatts.
+//!
+
+use super::*;
+
+/// The kind of a hardware monitor sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HwmonKind {
+    /// A temperature sensor (`tempN`)
+    Temperature,
+    /// A fan speed sensor (`fanN`)
+    Fan,
+    /// A voltage sensor (`inN`)
+    Voltage,
+    /// A current sensor (`currN`)
+    Current,
+    /// A power sensor (`powerN`)
+    Power,
+}
+
+impl HwmonKind {
+    /// Gets the kind of sensor from a channel ID, like "temp1".
+    pub fn from_id(id: &str) -> Option<Self> {
+        use HwmonKind::*;
+        let n = id.trim_end_matches(|c: char| c.is_ascii_digit());
+        if n.len() == id.len() {
+            return None;
+        }
+        match n {
+            "temp" => Some(Temperature),
+            "fan" => Some(Fan),
+            "in" => Some(Voltage),
+            "curr" => Some(Current),
+            "power" => Some(Power),
+            _ => None,
+        }
+    }
+
+    /// Gets the unit of the values reported by the sensor.
+    pub fn unit(&self) -> Unit {
+        use HwmonKind::*;
+        match *self {
+            Temperature => Unit::MillidegreesCelsius,
+            Fan => Unit::Rpm,
+            Voltage => Unit::Millivolts,
+            Current => Unit::Milliamps,
+            Power => Unit::Milliwatts,
+        }
+    }
+
+    /// Gets the factor to convert a sysfs value to the unit of the sensor.
+    fn scale(&self) -> f64 {
+        match *self {
+            HwmonKind::Power => 0.001,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A hardware monitor sensor.
+///
+/// This is a channel of a hardware monitor device.
+#[derive(Debug, Clone)]
+pub struct HwmonSensor {
+    /// The channel for the sensor
+    chan: Channel,
+    /// The kind of sensor
+    kind: HwmonKind,
+}
+
+impl HwmonSensor {
+    /// Gets the underlying channel for the sensor.
+    pub fn channel(&self) -> &Channel {
+        &self.chan
+    }
+
+    /// Gets the kind of sensor.
+    pub fn kind(&self) -> HwmonKind {
+        self.kind
+    }
+
+    /// Gets the ID of the sensor (e.g. <b><i>temp1</i></b>)
+    pub fn id(&self) -> Option<String> {
+        self.chan.id()
+    }
+
+    /// Gets the label of the sensor, if it has one, like "Core 0".
+    pub fn label(&self) -> Option<String> {
+        if !self.chan.has_attr("label") {
+            return None;
+        }
+        self.chan.attr_read_str("label").ok().map(|s| s.trim().to_string())
+    }
+
+    /// Determines if the sensor has the attribute, like "max" or "crit".
+    pub fn has_attr(&self, attr: &str) -> bool {
+        self.chan.has_attr(attr)
+    }
+
+    /// Reads a value attribute of the sensor, in the sensor's unit.
+    pub fn read(&self, attr: &str) -> Result<Measurement> {
+        let val = self.chan.attr_read_float(attr)?;
+        Ok(Measurement {
+            value: val * self.kind.scale(),
+            unit: self.kind.unit(),
+        })
+    }
+
+    /// Reads the current value of the sensor.
+    pub fn input(&self) -> Result<Measurement> {
+        self.read("input")
+    }
+
+    /// Reads the minimum value for the sensor.
+    pub fn min(&self) -> Result<Measurement> {
+        self.read("min")
+    }
+
+    /// Reads the maximum value for the sensor.
+    pub fn max(&self) -> Result<Measurement> {
+        self.read("max")
+    }
+
+    /// Reads the critical value for the sensor.
+    pub fn crit(&self) -> Result<Measurement> {
+        self.read("crit")
+    }
+
+    /// Determines if the sensor is in an alarm state.
+    pub fn alarm(&self) -> Result<bool> {
+        self.chan.attr_read_bool("alarm")
+    }
+}
+
+/// A hardware monitor device.
+///
+/// This is obtained from [`Context::hwmons()`], or by converting a device
+/// that is a hardware monitor.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// let ctx = iio::Context::new().unwrap();
+/// for hwmon in ctx.hwmons() {
+///     for sensor in hwmon.temperatures() {
+///         println!("{:?}: {}", sensor.label(), sensor.input().unwrap());
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hwmon {
+    /// The underlying hwmon device
+    dev: Device,
+}
+
+impl Hwmon {
+    /// Creates a hardware monitor from a device.
+    ///
+    /// This fails with [`Error::NotHwmon`] if the device is not a hardware
+    /// monitor.
+    pub fn from_device(dev: Device) -> Result<Self> {
+        if !dev.is_hwmon() {
+            return Err(Error::NotHwmon);
+        }
+        Ok(Self { dev })
+    }
+
+    /// Gets the underlying device.
+    pub fn device(&self) -> &Device {
+        &self.dev
+    }
+
+    /// Converts the hardware monitor back into a device.
+    pub fn into_device(self) -> Device {
+        self.dev
+    }
+
+    /// Gets the name of the hardware monitor, like "coretemp".
+    pub fn name(&self) -> Option<String> {
+        self.dev.name()
+    }
+
+    /// Gets an iterator for all the sensors of the device.
+    pub fn sensors(&self) -> impl Iterator<Item = HwmonSensor> + '_ {
+        self.dev.channels().filter_map(|chan| {
+            let kind = HwmonKind::from_id(&chan.id()?)?;
+            Some(HwmonSensor { chan, kind })
+        })
+    }
+
+    /// Gets an iterator for the sensors of the specified kind.
+    pub fn sensors_of(&self, kind: HwmonKind) -> impl Iterator<Item = HwmonSensor> + '_ {
+        self.sensors().filter(move |sensor| sensor.kind == kind)
+    }
+
+    /// Gets an iterator for the temperature sensors.
+    pub fn temperatures(&self) -> impl Iterator<Item = HwmonSensor> + '_ {
+        self.sensors_of(HwmonKind::Temperature)
+    }
+
+    /// Gets an iterator for the fan speed sensors.
+    pub fn fans(&self) -> impl Iterator<Item = HwmonSensor> + '_ {
+        self.sensors_of(HwmonKind::Fan)
+    }
+
+    /// Gets an iterator for the voltage sensors.
+    pub fn voltages(&self) -> impl Iterator<Item = HwmonSensor> + '_ {
+        self.sensors_of(HwmonKind::Voltage)
+    }
+
+    /// Gets an iterator for the current sensors.
+    pub fn currents(&self) -> impl Iterator<Item = HwmonSensor> + '_ {
+        self.sensors_of(HwmonKind::Current)
+    }
+
+    /// Gets an iterator for the power sensors.
+    pub fn powers(&self) -> impl Iterator<Item = HwmonSensor> + '_ {
+        self.sensors_of(HwmonKind::Power)
+    }
+
+    /// Try to find a sensor by its ID, like "temp1", or its label.
+    pub fn find_sensor(&self, name: &str) -> Option<HwmonSensor> {
+        self.sensors()
+            .find(|sensor| sensor.id().as_deref() == Some(name))
+            .or_else(|| self.sensors().find(|sensor| sensor.label().as_deref() == Some(name)))
+    }
+}
+
+impl AsRef<Device> for Hwmon {
+    fn as_ref(&self) -> &Device {
+        &self.dev
+    }
+}
+
+impl Context {
+    /// Gets an iterator for the hardware monitors in the context.
+    pub fn hwmons(&self) -> impl Iterator<Item = Hwmon> + '_ {
+        self.hwmon_devices().map(|dev| Hwmon { dev })
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The kind of sensor comes from the prefix of the channel ID.
+    #[test]
+    fn kind_from_id() {
+        assert_eq!(HwmonKind::from_id("temp1"), Some(HwmonKind::Temperature));
+        assert_eq!(HwmonKind::from_id("fan12"), Some(HwmonKind::Fan));
+        assert_eq!(HwmonKind::from_id("in0"), Some(HwmonKind::Voltage));
+        assert_eq!(HwmonKind::from_id("curr1"), Some(HwmonKind::Current));
+        assert_eq!(HwmonKind::from_id("power1"), Some(HwmonKind::Power));
+        assert_eq!(HwmonKind::from_id("temp"), None);
+        assert_eq!(HwmonKind::from_id("pwm1"), None);
+    }
+}
@@ -48,22 +48,64 @@ use std::{
     str::FromStr,
 };
 
-use libiio_sys::{self as ffi};
+/// The low-level bindings to the C library, for use with the raw handles
+/// of the wrapper types.
+pub use libiio_sys::{self as ffi};
 use nix::errno;
 
+#[cfg(feature = "ad9361")]
+pub use crate::ad9361::*;
 pub use crate::buffer::*;
+pub use crate::capture::*;
 pub use crate::channel::*;
 pub use crate::context::*;
+pub use crate::dds::*;
 pub use crate::device::*;
 pub use crate::errors::*;
+pub use crate::events::*;
+pub use crate::hotplug::*;
+pub use crate::hwmon::*;
+#[cfg(feature = "rayon")]
+pub use crate::par::*;
+pub use crate::pool::*;
+pub use crate::ring::*;
+pub use crate::scan::*;
+pub use crate::serial::*;
+pub use crate::sync_group::*;
+pub use crate::trigger::*;
+pub use crate::units::*;
+pub use crate::usb::*;
+pub use crate::watch::*;
+#[cfg(feature = "zeroconf")]
+pub use crate::zeroconf::*;
 
 mod macros;
 
+#[cfg(feature = "ad9361")]
+pub mod ad9361;
 pub mod buffer;
+pub mod capture;
 pub mod channel;
 pub mod context;
+pub mod dds;
 pub mod device;
 pub mod errors;
+pub mod events;
+pub mod hotplug;
+pub mod hwmon;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod pool;
+pub mod ring;
+pub mod scan;
+pub mod serial;
+pub mod sync_group;
+pub mod trigger;
+pub mod units;
+pub mod usb;
+pub mod watch;
+#[cfg(feature = "zeroconf")]
+pub mod zeroconf;
 
 /// According to the IIO samples, internal buffers need to be big enough
 /// for attributes coming back from the kernel.
@@ -153,6 +195,90 @@ impl FromAttribute for u128 {}
 impl FromAttribute for f64 {}
 impl FromAttribute for String {}
 
+/// The values accepted by an attribute, as reported by the driver.
+///
+/// Many drivers have `*_available` attributes, like
+/// `sampling_frequency_available` or `scale_available`, listing the values
+/// that can be written to the corresponding attribute. These are either a
+/// space-separated list of values, or a range in the form
+/// `"[min step max]"`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrChoices {
+    /// A discrete list of values
+    List(Vec<f64>),
+    /// A range of values, from `min` to `max`, in increments of `step`
+    Range {
+        /// The minimum value
+        min: f64,
+        /// The increment between values
+        step: f64,
+        /// The maximum value
+        max: f64,
+    },
+}
+
+impl AttrChoices {
+    /// Determines if the value is one of the choices.
+    ///
+    /// For a range, this only checks that the value lies between the
+    /// minimum and maximum, inclusive.
+    pub fn contains(&self, val: f64) -> bool {
+        match self {
+            AttrChoices::List(vals) => vals.contains(&val),
+            AttrChoices::Range { min, max, .. } => *min <= val && val <= *max,
+        }
+    }
+}
+
+impl FromStr for AttrChoices {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |s: &str| -> Result<Vec<f64>> {
+            s.split_whitespace()
+                .map(|v| v.parse().map_err(|_| Error::StringConversionError))
+                .collect()
+        };
+
+        let s = s.trim();
+        if s.starts_with('[') && s.ends_with(']') {
+            match parse(&s[1..s.len() - 1])?.as_slice() {
+                [min, step, max] => Ok(AttrChoices::Range {
+                    min: *min,
+                    step: *step,
+                    max: *max,
+                }),
+                _ => Err(Error::StringConversionError),
+            }
+        }
+        else {
+            Ok(AttrChoices::List(parse(s)?))
+        }
+    }
+}
+
+impl FromAttribute for AttrChoices {}
+
+/// The result of writing an attribute and reading it back.
+///
+/// Drivers often round or clamp the value written to an attribute, like a
+/// frequency or gain, to one that the hardware supports. This holds the
+/// value that was requested, along with the one that the driver accepted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerifiedWrite<T> {
+    /// The value that was written
+    pub requested: T,
+    /// The value that was read back after the write
+    pub effective: T,
+}
+
+impl<T: PartialEq> VerifiedWrite<T> {
+    /// Determines if the driver accepted the requested value as-is.
+    pub fn is_exact(&self) -> bool {
+        self.requested == self.effective
+    }
+}
+
 // Callback from the C lib to extract the collection of all
 // device-specific attributes. See attr_read_all().
 pub(crate) unsafe extern "C" fn attr_read_all_cb(
@@ -176,6 +302,32 @@ pub(crate) unsafe extern "C" fn attr_read_all_cb(
 
 // --------------------------------------------------------------------------
 
+/// Marker type for objects that are used for input from the hardware.
+#[derive(Debug, Clone, Copy)]
+pub struct Input;
+
+/// Marker type for objects that are used for output to the hardware.
+#[derive(Debug, Clone, Copy)]
+pub struct Output;
+
+/// Marker type for objects whose direction is only known at runtime.
+#[derive(Debug, Clone, Copy)]
+pub struct AnyDirection;
+
+/// Trait for the direction markers that allow input operations.
+pub trait InputDirection {}
+
+impl InputDirection for Input {}
+impl InputDirection for AnyDirection {}
+
+/// Trait for the direction markers that allow output operations.
+pub trait OutputDirection {}
+
+impl OutputDirection for Output {}
+impl OutputDirection for AnyDirection {}
+
+// --------------------------------------------------------------------------
+
 /// A struct to hold version numbers
 #[derive(Debug, PartialEq)]
 pub struct Version {
@@ -222,6 +374,25 @@ pub fn library_version() -> Version {
     }
 }
 
+/// Determines if the library was built with support for a backend.
+///
+/// `backend` The name of the backend, like "local", "xml", "ip", "usb",
+///     or "serial".
+pub fn has_backend(backend: &str) -> bool {
+    match CString::new(backend) {
+        Ok(cbackend) => unsafe { ffi::iio_has_backend(cbackend.as_ptr()) },
+        Err(_) => false,
+    }
+}
+
+/// Gets the names of the backends that the library was built to support.
+pub fn backends() -> Vec<String> {
+    let n = unsafe { ffi::iio_get_backends_count() };
+    (0..n)
+        .filter_map(|i| cstring_opt(unsafe { ffi::iio_get_backend(i) }))
+        .collect()
+}
+
 // --------------------------------------------------------------------------
 
 #[cfg(test)]
@@ -236,6 +407,15 @@ mod tests {
         assert!(v1 == v2);
     }
 
+    // Each of the backends reported by the library should be supported.
+    #[test]
+    fn backends_supported() {
+        for backend in backends() {
+            assert!(has_backend(&backend));
+        }
+        assert!(!has_backend("no-such-backend"));
+    }
+
     #[test]
     fn val_from_attr_str() {
         let val: i32 = i32::from_attr("123").unwrap();
@@ -268,4 +448,26 @@ mod tests {
         let s = String::to_attr(&"hello".to_string()).unwrap();
         assert_eq!(s.as_str(), "hello");
     }
+
+    #[test]
+    fn attr_choices() {
+        let choices = AttrChoices::from_attr("1000 2000 4000\n").unwrap();
+        assert_eq!(choices, AttrChoices::List(vec![1000.0, 2000.0, 4000.0]));
+        assert!(choices.contains(2000.0));
+        assert!(!choices.contains(3000.0));
+
+        let choices = AttrChoices::from_attr("[0 0.5 100]").unwrap();
+        assert_eq!(
+            choices,
+            AttrChoices::Range {
+                min: 0.0,
+                step: 0.5,
+                max: 100.0
+            }
+        );
+        assert!(choices.contains(42.0));
+
+        assert!(AttrChoices::from_attr("[0 100]").is_err());
+        assert!(AttrChoices::from_attr("low high").is_err());
+    }
 }
@@ -0,0 +1,231 @@
+// industrial-io/src/par.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Parallel iterators over buffer data, using Rayon.
+//!
+//! This is only available with the `rayon` feature. It allows compute-heavy
+//! transforms of the samples from a channel, like applying a calibration
+//! polynomial, to be spread over all the cores of the system.
+//!
+//! The data for a channel is obtained, and checked, with
+//! [`Buffer::par_channel_data()`], and implements `IntoParallelIterator`,
+//! so it can be used anywhere Rayon takes a parallel iterable.
+//!
+//! ```no_run
+//! use industrial_io as iio;
+//! use rayon::prelude::*;
+//!
+//! # fn run(buf: &iio::Buffer, chan: &iio::Channel) -> iio::Result<()> {
+//! let data = buf.par_channel_data::<i16>(chan)?;
+//! let volts: Vec<f64> = data.into_par_iter().map(|x| f64::from(x) * 0.5e-3).collect();
+//! # Ok(())
+//! # }
+//! ```
+//!
+
+use std::{marker::PhantomData, mem, ptr};
+
+use rayon::{
+    iter::{
+        plumbing::{Consumer, ProducerCallback, UnindexedConsumer},
+        IndexedParallelIterator, IntoParallelIterator, ParallelIterator,
+    },
+    slice::ParallelSlice,
+};
+
+use super::*;
+
+impl<D> Buffer<D> {
+    /// Gets the data from a channel, to be iterated in parallel.
+    ///
+    /// The samples are in the format used by the hardware. The size of the
+    /// sample type, `T`, must match the size of the samples for the
+    /// channel, otherwise an error is returned. The data that is returned
+    /// has been checked, and can be turned into a parallel iterator with
+    /// `into_par_iter()`.
+    pub fn par_channel_data<T>(&self, chan: &Channel) -> Result<ParChannelData<T>>
+    where
+        T: Copy + Send,
+    {
+        Self::check_sample_size::<T>(chan)?;
+        let step = self.step();
+        let offset = self.channel_offset(chan);
+        if step == 0 || offset + mem::size_of::<T>() > step {
+            return Err(Error::BadReturnSize);
+        }
+
+        Ok(ParChannelData {
+            phantom: PhantomData,
+            bytes: self.as_bytes(),
+            step,
+            offset,
+        })
+    }
+
+    /// Gets a parallel iterator for the data from a channel.
+    ///
+    /// This is the parallel version of [`Buffer::channel_iter()`], and is
+    /// the same as calling `into_par_iter()` on the data from
+    /// [`Buffer::par_channel_data()`].
+    pub fn par_channel_iter<T>(&self, chan: &Channel) -> Result<ParChannelIter<T>>
+    where
+        T: Copy + Send,
+    {
+        Ok(self.par_channel_data(chan)?.into_par_iter())
+    }
+}
+
+/// The data from a channel of a buffer, to be iterated in parallel.
+///
+/// This is obtained from [`Buffer::par_channel_data()`], which checks
+/// that the samples are of the type `T`.
+/// 'a Lifetime of the Buffer
+#[derive(Debug, Clone, Copy)]
+pub struct ParChannelData<'a, T> {
+    phantom: PhantomData<T>,
+    // The raw contents of the buffer
+    bytes: &'a [u8],
+    // The number of bytes from one sample set to the next
+    step: usize,
+    // The byte offset of the channel's sample in each set
+    offset: usize,
+}
+
+impl<'a, T> ParChannelData<'a, T> {
+    /// Gets the number of samples.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / self.step
+    }
+
+    /// Determines if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, T: Copy + Send> IntoParallelIterator for ParChannelData<'a, T> {
+    type Iter = ParChannelIter<'a, T>;
+    type Item = T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParChannelIter {
+            phantom: PhantomData,
+            bytes: self.bytes,
+            step: self.step,
+            offset: self.offset,
+        }
+    }
+}
+
+impl<'a, T: Copy + Send> IntoParallelIterator for &ParChannelData<'a, T> {
+    type Iter = ParChannelIter<'a, T>;
+    type Item = T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        (*self).into_par_iter()
+    }
+}
+
+/// A parallel iterator over the samples from a channel.
+///
+/// This is obtained from [`Buffer::par_channel_iter()`], or from the
+/// `into_par_iter()` of a [`ParChannelData`].
+/// 'a Lifetime of the Buffer
+#[derive(Debug, Clone)]
+pub struct ParChannelIter<'a, T> {
+    phantom: PhantomData<T>,
+    // The raw contents of the buffer
+    bytes: &'a [u8],
+    // The number of bytes from one sample set to the next
+    step: usize,
+    // The byte offset of the channel's sample in each set
+    offset: usize,
+}
+
+impl<'a, T: Copy + Send> ParChannelIter<'a, T> {
+    // Gets an indexed parallel iterator over the samples.
+    fn samples(self) -> impl IndexedParallelIterator<Item = T> + 'a {
+        let offset = self.offset;
+        self.bytes
+            .par_chunks_exact(self.step)
+            .map(move |frame| unsafe { ptr::read_unaligned(frame[offset..].as_ptr().cast::<T>()) })
+    }
+}
+
+impl<'a, T: Copy + Send> ParallelIterator for ParChannelIter<'a, T> {
+    type Item = T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.samples().drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(IndexedParallelIterator::len(self))
+    }
+}
+
+impl<'a, T: Copy + Send> IndexedParallelIterator for ParChannelIter<'a, T> {
+    fn len(&self) -> usize {
+        self.bytes.len() / self.step
+    }
+
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        self.samples().drive(consumer)
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        self.samples().with_producer(callback)
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The samples of a channel are picked out of each sample set, in
+    // order, like the serial channel iterators.
+    #[test]
+    fn par_channel_data_iter() {
+        // Sample sets of a u16 channel, then an i16 channel, in native order
+        let mut bytes = Vec::new();
+        for (a, b) in &[(1u16, -1i16), (2, -2), (3, -3)] {
+            bytes.extend_from_slice(&a.to_ne_bytes());
+            bytes.extend_from_slice(&b.to_ne_bytes());
+        }
+
+        let data: ParChannelData<i16> = ParChannelData {
+            phantom: PhantomData,
+            bytes: &bytes,
+            step: 4,
+            offset: 2,
+        };
+        assert_eq!(data.len(), 3);
+
+        let v: Vec<i16> = (&data).into_par_iter().collect();
+        assert_eq!(v, vec![-1, -2, -3]);
+
+        let iter = data.into_par_iter();
+        assert_eq!(IndexedParallelIterator::len(&iter), 3);
+        let v: Vec<i32> = iter.map(|x| i32::from(x) * 10).collect();
+        assert_eq!(v, vec![-10, -20, -30]);
+    }
+}
@@ -0,0 +1,286 @@
+// industrial-io/src/pool.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Pools of buffers refilled in the background.
+//!
+//! The call to [`Buffer::refill()`] blocks until the hardware has captured
+//! a full buffer of samples. An application that processes the data in the
+//! same thread as it captures it can then miss samples while it's busy.
+//!
+//! A [`BufferPool`] hides the latency of the refill by moving the capture
+//! to a worker thread. The worker owns the input buffer for the device,
+//! and copies the data from each refill into one of a fixed number of
+//! blocks of memory. The caller gets the next filled block with
+//! [`BufferPool::next_filled()`] and processes it while the worker is
+//! refilling the next one. When the caller is done with a block, it is
+//! dropped and returned to the pool to be filled again.
+//!
+//! With a depth of two, this is the classic double buffering scheme.
+//!
+
+use std::{
+    mem,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use super::*;
+
+/// The layout of the sample sets copied out of a buffer.
+#[derive(Debug, Clone)]
+pub(crate) struct Layout {
+    /// The number of bytes from one sample set to the next
+    pub(crate) step: usize,
+    /// The byte offset of each enabled channel, keyed by channel ID
+    offsets: Vec<(String, usize)>,
+}
+
+impl Layout {
+    /// Gets the layout of the data in the buffer.
+    pub(crate) fn new<D>(buf: &Buffer<D>) -> Self {
+        let offsets = buf
+            .device()
+            .channels()
+            .filter(|chan| chan.is_enabled() && !chan.is_output())
+            .map(|chan| (chan.id().unwrap_or_default(), buf.channel_offset(&chan)))
+            .collect();
+
+        Self {
+            step: buf.step(),
+            offsets,
+        }
+    }
+
+    /// Gets the byte offset of the channel's samples in each sample set.
+    fn offset(&self, chan: &Channel) -> Result<usize> {
+        let id = chan.id().unwrap_or_default();
+        self.offsets
+            .iter()
+            .find(|(chan_id, _)| *chan_id == id)
+            .map(|(_, off)| *off)
+            .ok_or(Error::ChannelNotEnabled)
+    }
+
+    /// Gets the number of sample sets in a block of data.
+    pub(crate) fn len(&self, data: &[u8]) -> usize {
+        data.len().checked_div(self.step).unwrap_or(0)
+    }
+
+    /// Gets an iterator for the samples from a channel in a block of data.
+    pub(crate) fn channel_iter<'a, T: Copy>(
+        &self,
+        data: &'a [u8],
+        chan: &Channel,
+    ) -> Result<impl Iterator<Item = T> + 'a> {
+        Buffer::<AnyDirection>::check_sample_size::<T>(chan)?;
+        let off = self.offset(chan)?;
+        if off + mem::size_of::<T>() > self.step {
+            return Err(Error::BadReturnSize);
+        }
+
+        Ok(data
+            .chunks_exact(self.step)
+            .map(move |frame| unsafe { ptr::read_unaligned(frame[off..].as_ptr().cast::<T>()) }))
+    }
+}
+
+/// A pool of input buffers that are refilled on a worker thread.
+///
+/// See the [module documentation](crate::pool) for details.
+#[derive(Debug)]
+pub struct BufferPool {
+    /// The layout of the data in the blocks
+    layout: Arc<Layout>,
+    /// Receives the filled blocks from the worker
+    filled: mpsc::Receiver<Result<Vec<u8>>>,
+    /// Sends empty blocks back to the worker, or `None` to stop it
+    free: mpsc::Sender<Option<Vec<u8>>>,
+    /// Token to cancel a refill that is in progress
+    token: CancelToken,
+    /// Flag to signal the worker to stop
+    stop: Arc<AtomicBool>,
+    /// The worker thread
+    thr: Option<JoinHandle<()>>,
+}
+
+impl BufferPool {
+    /// Creates a pool of `depth` blocks, each filled from an input buffer
+    /// created with the options in the builder.
+    ///
+    /// The channels for the device must be enabled before the pool is
+    /// created. The buffer is created in the worker thread, and any error
+    /// creating it is returned from here.
+    pub(crate) fn new(builder: BufferBuilder, depth: usize) -> Result<Self> {
+        let (setup_tx, setup_rx) = mpsc::channel();
+        let (filled_tx, filled) = mpsc::channel();
+        let (free, free_rx) = mpsc::channel::<Option<Vec<u8>>>();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thr_stop = Arc::clone(&stop);
+
+        let thr = thread::spawn(move || {
+            let mut buf = match builder.create_input() {
+                Ok(buf) => buf,
+                Err(err) => {
+                    let _ = setup_tx.send(Err(err));
+                    return;
+                }
+            };
+            let _ = setup_tx.send(Ok((Layout::new(&buf), buf.cancel_token())));
+
+            while let Ok(Some(mut block)) = free_rx.recv() {
+                let res = buf.refill();
+                if thr_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let res = res.map(|_| {
+                    block.clear();
+                    block.extend_from_slice(buf.as_bytes());
+                    block
+                });
+                let is_err = res.is_err();
+                if filled_tx.send(res).is_err() || is_err {
+                    break;
+                }
+            }
+        });
+
+        let (layout, token) = match setup_rx.recv() {
+            Ok(Ok(setup)) => setup,
+            Ok(Err(err)) => {
+                let _ = thr.join();
+                return Err(err);
+            }
+            Err(_) => {
+                let _ = thr.join();
+                return Err(Error::General("Buffer pool worker failed to start".into()));
+            }
+        };
+
+        for _ in 0..depth.max(1) {
+            let _ = free.send(Some(Vec::new()));
+        }
+
+        Ok(Self {
+            layout: Arc::new(layout),
+            filled,
+            free,
+            token,
+            stop,
+            thr: Some(thr),
+        })
+    }
+
+    /// Gets the next filled block from the pool.
+    ///
+    /// This blocks until the worker has completed a refill. If the refill
+    /// failed, the error is returned, and the worker stops. After that,
+    /// any further calls will also return an error.
+    pub fn next_filled(&self) -> Result<PooledBuffer> {
+        let data = self
+            .filled
+            .recv()
+            .map_err(|_| Error::General("Buffer pool worker has stopped".into()))??;
+
+        Ok(PooledBuffer {
+            data,
+            layout: Arc::clone(&self.layout),
+            free: self.free.clone(),
+        })
+    }
+
+    /// Gets the next filled block from the pool, if one is ready.
+    ///
+    /// This does not block. It returns `Ok(None)` if the worker has not
+    /// yet completed the next refill.
+    pub fn try_next_filled(&self) -> Result<Option<PooledBuffer>> {
+        let data = match self.filled.try_recv() {
+            Ok(res) => res?,
+            Err(mpsc::TryRecvError::Empty) => return Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                return Err(Error::General("Buffer pool worker has stopped".into()))
+            }
+        };
+
+        Ok(Some(PooledBuffer {
+            data,
+            layout: Arc::clone(&self.layout),
+            free: self.free.clone(),
+        }))
+    }
+}
+
+/// Stops the worker and destroys the buffer when the pool is dropped.
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.free.send(None);
+        self.token.cancel();
+        if let Some(thr) = self.thr.take() {
+            let _ = thr.join();
+        }
+    }
+}
+
+/// A block of data, filled from a refill of the buffer in a pool.
+///
+/// This holds a copy of the raw contents of the buffer, in the format
+/// used by the hardware. It is returned to the pool, to be filled again,
+/// when it is dropped.
+#[derive(Debug)]
+pub struct PooledBuffer {
+    /// The raw data from the buffer
+    data: Vec<u8>,
+    /// The layout of the data
+    layout: Arc<Layout>,
+    /// To return the block to the pool
+    free: mpsc::Sender<Option<Vec<u8>>>,
+}
+
+impl PooledBuffer {
+    /// Gets the number of bytes from one sample set to the next.
+    pub fn step(&self) -> usize {
+        self.layout.step
+    }
+
+    /// Gets the number of sample sets in the block.
+    pub fn len(&self) -> usize {
+        self.layout.len(&self.data)
+    }
+
+    /// Determines if the block is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the raw contents of the block as a slice of bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Gets an iterator for the data from a channel.
+    ///
+    /// The channel must have been enabled when the pool was created, and
+    /// the size of the sample type, `T`, must match the size of the samples
+    /// for the channel, otherwise an error is returned. The samples are in
+    /// the format used by the hardware.
+    pub fn channel_iter<T: Copy>(&self, chan: &Channel) -> Result<impl Iterator<Item = T> + '_> {
+        self.layout.channel_iter(&self.data, chan)
+    }
+}
+
+/// Returns the block to the pool when it is dropped.
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let _ = self.free.send(Some(mem::take(&mut self.data)));
+    }
+}
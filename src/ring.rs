@@ -0,0 +1,190 @@
+// industrial-io/src/ring.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! A bounded ring to pass frames between threads.
+//!
+//! A common pattern for real-time capture is to have one thread refilling
+//! a buffer and demultiplexing the frames from it, while another thread
+//! processes them. The capture thread should never block on the consumer,
+//! otherwise it will fall behind the hardware.
+//!
+//! A ring created by [`frame_ring()`] connects the two. The capture thread
+//! pushes frames into the [`RingProducer`] without blocking. If the ring is
+//! full, the frame is dropped and counted as an overrun. The consumer
+//! thread takes the frames from the [`RingConsumer`] in the order that they
+//! were pushed.
+//!
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+/// Counters shared by both ends of a ring.
+#[derive(Debug, Default)]
+struct RingStats {
+    /// The number of frames pushed into the ring
+    pushed: AtomicU64,
+    /// The number of frames dropped because the ring was full
+    overruns: AtomicU64,
+}
+
+/// Creates a ring that can hold up to `capacity` frames.
+///
+/// This returns the producer and consumer ends of the ring, which can be
+/// moved to different threads, as long as the frame type is `Send`.
+pub fn frame_ring<T>(capacity: usize) -> (RingProducer<T>, RingConsumer<T>) {
+    let (tx, rx) = mpsc::sync_channel(capacity.max(1));
+    let stats = Arc::new(RingStats::default());
+
+    let prod = RingProducer {
+        tx,
+        stats: Arc::clone(&stats),
+    };
+    let cons = RingConsumer { rx, stats };
+    (prod, cons)
+}
+
+/// The end of a ring that is written by the capture thread.
+#[derive(Debug)]
+pub struct RingProducer<T> {
+    tx: mpsc::SyncSender<T>,
+    stats: Arc<RingStats>,
+}
+
+impl<T> RingProducer<T> {
+    /// Pushes a frame into the ring, without blocking.
+    ///
+    /// This returns `true` if the frame was added to the ring. If the ring
+    /// is full, the frame is dropped, counted as an overrun, and this
+    /// returns `false`. It also returns `false` if the consumer is gone.
+    pub fn push(&self, frame: T) -> bool {
+        match self.tx.try_send(frame) {
+            Ok(()) => {
+                self.stats.pushed.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(mpsc::TrySendError::Full(_)) => {
+                self.stats.overruns.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    /// Pushes all the frames from an iterator into the ring.
+    ///
+    /// This returns the number of frames that were added. Any that didn't
+    /// fit are counted as overruns.
+    pub fn push_all<I>(&self, frames: I) -> usize
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut n = 0;
+        for frame in frames {
+            if self.push(frame) {
+                n += 1;
+            }
+        }
+        n
+    }
+
+    /// Gets the total number of frames pushed into the ring.
+    pub fn pushed(&self) -> u64 {
+        self.stats.pushed.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of frames dropped because the ring was full.
+    pub fn overruns(&self) -> u64 {
+        self.stats.overruns.load(Ordering::Relaxed)
+    }
+}
+
+/// The end of a ring that is drained by the processing thread.
+#[derive(Debug)]
+pub struct RingConsumer<T> {
+    rx: mpsc::Receiver<T>,
+    stats: Arc<RingStats>,
+}
+
+impl<T> RingConsumer<T> {
+    /// Takes the next frame from the ring, blocking until one is available.
+    ///
+    /// This returns `None` when the ring is empty and the producer is gone.
+    pub fn recv(&self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+
+    /// Takes the next frame from the ring, waiting up to the specified
+    /// time for one to become available.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<T> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+
+    /// Takes the next frame from the ring, if one is available, without
+    /// blocking.
+    pub fn try_recv(&self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Gets an iterator that takes all the frames currently in the ring,
+    /// without blocking.
+    pub fn drain(&self) -> impl Iterator<Item = T> + '_ {
+        self.rx.try_iter()
+    }
+
+    /// Gets the total number of frames pushed into the ring.
+    pub fn pushed(&self) -> u64 {
+        self.stats.pushed.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of frames dropped because the ring was full.
+    pub fn overruns(&self) -> u64 {
+        self.stats.overruns.load(Ordering::Relaxed)
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // Frames that don't fit in the ring are dropped and counted.
+    #[test]
+    fn overruns() {
+        let (prod, cons) = frame_ring(2);
+        assert_eq!(prod.push_all(1..=5), 2);
+        assert_eq!(prod.pushed(), 2);
+        assert_eq!(cons.overruns(), 3);
+        assert_eq!(cons.drain().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(prod.push(6));
+    }
+
+    // Frames are passed between threads in order.
+    #[test]
+    fn across_threads() {
+        let (prod, cons) = frame_ring(16);
+        let thr = thread::spawn(move || {
+            for i in 0..10 {
+                prod.push(i);
+            }
+        });
+        thr.join().unwrap();
+
+        let v: Vec<_> = std::iter::from_fn(|| cons.recv()).collect();
+        assert_eq!(v, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,146 @@
+// industrial-io/src/scan.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Scanning for the available contexts.
+//!
+//! The C library can search for the contexts that it is able to create,
+//! such as the local system, USB devices, and network hosts that run the
+//! IIO daemon. Each is reported with the URI to create it, and a
+//! description that can be shown to the user.
+//!
+
+use std::{ffi::CString, os::raw::c_uint, ptr};
+
+use super::*;
+use nix::errno::Errno;
+
+/// Information about a context that was found by a scan.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContextInfo {
+    /// The URI to create the context, like "usb:3.32.5"
+    pub uri: String,
+    /// A description of the context
+    pub description: String,
+}
+
+impl ContextInfo {
+    /// Gets the information from a C context info pointer.
+    fn new(info: *const ffi::iio_context_info) -> Self {
+        unsafe {
+            Self {
+                uri: cstring_opt(ffi::iio_context_info_get_uri(info)).unwrap_or_default(),
+                description: cstring_opt(ffi::iio_context_info_get_description(info))
+                    .unwrap_or_default(),
+            }
+        }
+    }
+
+    /// Creates the context.
+    pub fn create_context(&self) -> Result<Context> {
+        Context::from_uri(&self.uri)
+    }
+}
+
+/// Converts the optional backends into a C string.
+fn backend_cstring(backends: Option<&str>) -> Result<Option<CString>> {
+    Ok(match backends {
+        Some(s) => Some(CString::new(s)?),
+        None => None,
+    })
+}
+
+/// Scans for the available contexts.
+///
+/// `backends` The backends to scan, separated by colons, like "usb" or
+///     "ip:usb", or `None` to scan all the backends that the library
+///     supports.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// for info in iio::scan_contexts(None).unwrap() {
+///     println!("{}: {}", info.uri, info.description);
+/// }
+/// ```
+pub fn scan_contexts(backends: Option<&str>) -> Result<Vec<ContextInfo>> {
+    let cbackends = backend_cstring(backends)?;
+    let pbackends = cbackends.as_ref().map_or(ptr::null(), |s| s.as_ptr());
+
+    let ctx = unsafe { ffi::iio_create_scan_context(pbackends, 0) };
+    if ctx.is_null() {
+        return Err(Errno::last().into());
+    }
+
+    let mut list = ptr::null_mut();
+    let ret = unsafe { ffi::iio_scan_context_get_info_list(ctx, &mut list) };
+
+    let res = sys_result(ret as i32, ret as usize).map(|n| {
+        (0..n).map(|i| ContextInfo::new(unsafe { *list.add(i) })).collect()
+    });
+
+    unsafe {
+        if !list.is_null() {
+            ffi::iio_context_info_list_free(list);
+        }
+        ffi::iio_scan_context_destroy(ctx);
+    }
+    res
+}
+
+/// A reusable scanner for the contexts of a backend.
+///
+/// This can be used to scan repeatedly, such as to update a list of the
+/// devices that the user can pick from.
+#[derive(Debug)]
+pub struct ScanBlock {
+    /// The underlying scan block from the C library
+    blk: *mut ffi::iio_scan_block,
+}
+
+impl ScanBlock {
+    /// Creates a scanner for the backend, like "usb" or "ip", or for all
+    /// the supported backends if `None`.
+    pub fn new(backend: Option<&str>) -> Result<Self> {
+        let cbackend = backend_cstring(backend)?;
+        let pbackend = cbackend.as_ref().map_or(ptr::null(), |s| s.as_ptr());
+
+        let blk = unsafe { ffi::iio_create_scan_block(pbackend, 0) };
+        if blk.is_null() {
+            return Err(Errno::last().into());
+        }
+        Ok(Self { blk })
+    }
+
+    /// Scans for the available contexts.
+    pub fn scan(&mut self) -> Result<Vec<ContextInfo>> {
+        let ret = unsafe { ffi::iio_scan_block_scan(self.blk) };
+        let n = sys_result(ret as i32, ret as usize)?;
+
+        let infos = (0..n)
+            .filter_map(|i| {
+                let info = unsafe { ffi::iio_scan_block_get_info(self.blk, i as c_uint) };
+                if info.is_null() {
+                    None
+                }
+                else {
+                    Some(ContextInfo::new(info))
+                }
+            })
+            .collect();
+        Ok(infos)
+    }
+}
+
+impl Drop for ScanBlock {
+    fn drop(&mut self) {
+        unsafe { ffi::iio_scan_block_destroy(self.blk) };
+    }
+}
@@ -0,0 +1,221 @@
+// industrial-io/src/serial.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Configuration for contexts that use the serial backend.
+//!
+//! The IIO daemon can be reached over a UART, such as on an embedded
+//! target without a network connection. A [`SerialConfig`] describes the
+//! port and its settings, and formats them into the parameters of the
+//! "serial:" URI expected by the C library.
+//!
+
+use std::fmt;
+
+use super::*;
+
+/// The parity of a serial port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parity {
+    /// No parity bit
+    None,
+    /// Odd parity
+    Odd,
+    /// Even parity
+    Even,
+    /// The parity bit is always 1
+    Mark,
+    /// The parity bit is always 0
+    Space,
+}
+
+impl Parity {
+    /// Gets the character for the parity in a serial URI.
+    fn as_char(&self) -> char {
+        use Parity::*;
+        match *self {
+            None => 'n',
+            Odd => 'o',
+            Even => 'e',
+            Mark => 'm',
+            Space => 's',
+        }
+    }
+}
+
+/// The flow control of a serial port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlowControl {
+    /// No flow control
+    None,
+    /// Software flow control, with XON/XOFF characters
+    XonXoff,
+    /// Hardware flow control, with the RTS and CTS lines
+    RtsCts,
+    /// Hardware flow control, with the DTR and DSR lines
+    DtrDsr,
+}
+
+impl FlowControl {
+    /// Gets the character for the flow control in a serial URI, if any.
+    fn as_char(&self) -> Option<char> {
+        use FlowControl::*;
+        match *self {
+            None => Option::None,
+            XonXoff => Some('x'),
+            RtsCts => Some('r'),
+            DtrDsr => Some('d'),
+        }
+    }
+}
+
+/// The configuration of a serial port for a context.
+///
+/// This formats into the parameters of the URI for the serial backend,
+/// like "/dev/ttyUSB0,115200,8n1".
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// let cfg = iio::SerialConfig::new("/dev/ttyUSB0")
+///     .baud_rate(57600)
+///     .flow_control(iio::FlowControl::RtsCts);
+/// let ctx = iio::Context::from_serial(&cfg).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SerialConfig {
+    /// The path to the serial port device
+    port: String,
+    /// The baud rate
+    baud_rate: u32,
+    /// The number of data bits, from 5 to 9
+    data_bits: u8,
+    /// The parity
+    parity: Parity,
+    /// The number of stop bits, 1 or 2
+    stop_bits: u8,
+    /// The flow control
+    flow_control: FlowControl,
+}
+
+impl SerialConfig {
+    /// Creates a configuration for the port, with the default settings of
+    /// 115200 baud, 8 data bits, no parity, 1 stop bit, and no flow
+    /// control.
+    pub fn new(port: &str) -> Self {
+        Self {
+            port: port.to_string(),
+            baud_rate: 115200,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+            flow_control: FlowControl::None,
+        }
+    }
+
+    /// Sets the baud rate.
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Sets the number of data bits, from 5 to 9.
+    pub fn data_bits(mut self, n: u8) -> Self {
+        self.data_bits = n;
+        self
+    }
+
+    /// Sets the parity.
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Sets the number of stop bits, 1 or 2.
+    pub fn stop_bits(mut self, n: u8) -> Self {
+        self.stop_bits = n;
+        self
+    }
+
+    /// Sets the flow control.
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// Checks that the settings are ones that the serial backend accepts.
+    pub fn validate(&self) -> Result<()> {
+        if self.port.is_empty() || self.port.contains(',') {
+            return Err(Error::General(format!("Invalid serial port: {}", self.port)));
+        }
+        if !(5..=9).contains(&self.data_bits) {
+            return Err(Error::General(format!("Invalid data bits: {}", self.data_bits)));
+        }
+        if !(1..=2).contains(&self.stop_bits) {
+            return Err(Error::General(format!("Invalid stop bits: {}", self.stop_bits)));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SerialConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}{}{}",
+            self.port,
+            self.baud_rate,
+            self.data_bits,
+            self.parity.as_char(),
+            self.stop_bits
+        )?;
+        if let Some(c) = self.flow_control.as_char() {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+impl Context {
+    /// Creates a context using the serial backend, with the port and its
+    /// settings.
+    pub fn from_serial(cfg: &SerialConfig) -> Result<Self> {
+        cfg.validate()?;
+        Self::with_backend(Backend::Serial(&cfg.to_string()))
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The configuration formats into the serial URI parameters.
+    #[test]
+    fn serial_config_params() {
+        let cfg = SerialConfig::new("/dev/ttyUSB0");
+        assert_eq!(cfg.to_string(), "/dev/ttyUSB0,115200,8n1");
+
+        let cfg = SerialConfig::new("/dev/ttyS1")
+            .baud_rate(9600)
+            .data_bits(7)
+            .parity(Parity::Even)
+            .stop_bits(2)
+            .flow_control(FlowControl::XonXoff);
+        assert_eq!(cfg.to_string(), "/dev/ttyS1,9600,7e2x");
+        assert!(cfg.validate().is_ok());
+
+        assert!(SerialConfig::new("/dev/ttyS1").data_bits(4).validate().is_err());
+        assert!(SerialConfig::new("/dev/ttyS1").stop_bits(3).validate().is_err());
+    }
+}
@@ -0,0 +1,144 @@
+// industrial-io/src/sync_group.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Synchronized capture from multiple devices.
+//!
+//! A [`SyncGroup`] creates input buffers for several devices, such that
+//! they start capturing at, as close as possible, the same time. This is
+//! needed for phase-coherent captures with multiple ADCs.
+//!
+//! Devices that support hardware synchronization, through the
+//! `sync_start_enable` attribute of the ADI AXI ADC cores, are armed
+//! before their buffers are created, and then started together by a
+//! single trigger. Other devices are started in software, one right after
+//! another, which leaves some skew between them.
+//!
+
+use super::*;
+
+/// The device attribute to arm and trigger a synchronized start.
+const SYNC_START_ATTR: &str = "sync_start_enable";
+
+/// A group of input buffers, for several devices, that are started
+/// together.
+///
+/// The channels must be enabled on each device before the group is
+/// created.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+///
+/// let ctx = iio::Context::new().unwrap();
+/// let devs = [
+///     ctx.find_device("axi-ad9680-hpc").unwrap(),
+///     ctx.find_device("axi-ad9680-hpc-1").unwrap(),
+/// ];
+/// for dev in &devs {
+///     dev.enable_all_scan_elements().unwrap();
+/// }
+///
+/// let mut group = iio::SyncGroup::new(&devs, 8192).unwrap();
+/// group.refill().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SyncGroup {
+    /// The buffers, in the order of the devices
+    bufs: Vec<InputBuffer>,
+    /// Whether the buffers were started by a hardware trigger
+    hw_sync: bool,
+}
+
+impl SyncGroup {
+    /// Creates the input buffers for the devices, and starts them.
+    ///
+    /// If all the devices support hardware synchronization, they are
+    /// armed, the buffers are created, and then the first device triggers
+    /// the start of all of them. Otherwise the buffers are created one
+    /// after another, with nothing in between.
+    ///
+    /// `sample_count` The number of samples each buffer should hold
+    pub fn new(devs: &[Device], sample_count: usize) -> Result<Self> {
+        let hw_sync = !devs.is_empty() && devs.iter().all(|dev| dev.has_attr(SYNC_START_ATTR));
+
+        if hw_sync {
+            for dev in devs {
+                dev.attr_write_str(SYNC_START_ATTR, "arm")?;
+            }
+        }
+
+        let bufs = devs
+            .iter()
+            .map(|dev| dev.create_input_buffer(sample_count))
+            .collect::<Result<Vec<_>>>();
+
+        let bufs = match bufs {
+            Ok(bufs) => bufs,
+            Err(err) => {
+                if hw_sync {
+                    for dev in devs {
+                        let _ = dev.attr_write_str(SYNC_START_ATTR, "disarm");
+                    }
+                }
+                return Err(err);
+            }
+        };
+
+        if hw_sync {
+            devs[0].attr_write_str(SYNC_START_ATTR, "trigger_manual")?;
+        }
+        Ok(Self { bufs, hw_sync })
+    }
+
+    /// Determines if the buffers were started by a hardware trigger.
+    pub fn is_hw_synced(&self) -> bool {
+        self.hw_sync
+    }
+
+    /// Gets the number of buffers in the group.
+    pub fn len(&self) -> usize {
+        self.bufs.len()
+    }
+
+    /// Determines if the group has no buffers.
+    pub fn is_empty(&self) -> bool {
+        self.bufs.is_empty()
+    }
+
+    /// Gets the buffers, in the order of the devices.
+    pub fn buffers(&self) -> &[InputBuffer] {
+        &self.bufs
+    }
+
+    /// Gets mutable access to the buffers, in the order of the devices.
+    pub fn buffers_mut(&mut self) -> &mut [InputBuffer] {
+        &mut self.bufs
+    }
+
+    /// Gets the buffer for the device at the index.
+    pub fn buffer(&self, idx: usize) -> Option<&InputBuffer> {
+        self.bufs.get(idx)
+    }
+
+    /// Consumes the group, returning the buffers.
+    pub fn into_buffers(self) -> Vec<InputBuffer> {
+        self.bufs
+    }
+
+    /// Refills all the buffers, returning the number of bytes read into
+    /// each.
+    ///
+    /// The buffers are refilled one after another. Since they were started
+    /// together and hold the same number of samples, each holds the same
+    /// span of time after the refill.
+    pub fn refill(&mut self) -> Result<Vec<usize>> {
+        self.bufs.iter_mut().map(|buf| buf.refill()).collect()
+    }
+}
@@ -0,0 +1,211 @@
+// industrial-io/src/trigger.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Industrial I/O Triggers
+//!
+//! In the C library, a trigger is just a device that reports itself as a
+//! trigger. A [`Trigger`] wraps one of these devices so that it can't be
+//! confused with a device that produces data.
+//!
+//! With the local backend, software triggers can also be created through
+//! the `iio-trig-sysfs` kernel module. These are fired by the application
+//! with [`Trigger::trigger_now()`].
+//!
+
+use std::{fs, ptr};
+
+use super::*;
+
+/// The sysfs directory for creating and removing software triggers.
+const SYSFS_TRIGGER_PATH: &str = "/sys/bus/iio/devices/iio_sysfs_trigger";
+
+/// An Industrial I/O Trigger
+///
+/// This is obtained from [`Context::triggers()`] or [`Device::trigger()`],
+/// or by converting a device that is a trigger.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trigger {
+    /// The underlying trigger device
+    dev: Device,
+}
+
+impl Trigger {
+    /// Creates a trigger from a device.
+    ///
+    /// This fails with [`Error::NotTrigger`] if the device is not a trigger.
+    pub fn from_device(dev: Device) -> Result<Self> {
+        if !dev.is_trigger() {
+            return Err(Error::NotTrigger);
+        }
+        Ok(Self { dev })
+    }
+
+    /// Gets the underlying trigger device.
+    pub fn device(&self) -> &Device {
+        &self.dev
+    }
+
+    /// Converts the trigger back into a device.
+    pub fn into_device(self) -> Device {
+        self.dev
+    }
+
+    /// Gets the ID of the trigger (e.g. <b><i>trigger0</i></b>)
+    pub fn id(&self) -> Option<String> {
+        self.dev.id()
+    }
+
+    /// Gets the name of the trigger
+    pub fn name(&self) -> Option<String> {
+        self.dev.name()
+    }
+
+    /// Gets the frequency at which the trigger fires.
+    ///
+    /// This reads the `sampling_frequency` attribute of the trigger.
+    pub fn frequency(&self) -> Result<Hertz> {
+        self.dev.attr_read("sampling_frequency")
+    }
+
+    /// Sets the frequency at which the trigger fires.
+    pub fn set_frequency(&self, freq: Hertz) -> Result<()> {
+        self.dev.attr_write("sampling_frequency", freq)
+    }
+
+    /// Fires the trigger.
+    ///
+    /// This writes the `trigger_now` attribute, which is only available for
+    /// software triggers, like those created with
+    /// [`Trigger::create_sysfs()`].
+    pub fn trigger_now(&self) -> Result<()> {
+        self.dev.attr_write_bool("trigger_now", true)
+    }
+
+    /// Creates a sysfs software trigger on the local system.
+    ///
+    /// This requires the `iio-trig-sysfs` kernel module, and permission to
+    /// write to its sysfs directory. The trigger is named "sysfstrigN",
+    /// where N is the `id`.
+    ///
+    /// Existing contexts don't see devices that were created after them,
+    /// so the trigger is returned from a new local context. It can still
+    /// be assigned to the devices of other local contexts.
+    pub fn create_sysfs(id: u32) -> Result<Self> {
+        fs::write(format!("{}/add_trigger", SYSFS_TRIGGER_PATH), id.to_string())?;
+        Context::with_backend(Backend::Local)?
+            .find_trigger(&format!("sysfstrig{}", id))
+            .ok_or_else(|| Error::General(format!("Trigger not found: sysfstrig{}", id)))
+    }
+
+    /// Removes a sysfs software trigger from the local system.
+    ///
+    /// The trigger must not be in use by any device.
+    pub fn remove_sysfs(id: u32) -> Result<()> {
+        fs::write(format!("{}/remove_trigger", SYSFS_TRIGGER_PATH), id.to_string())?;
+        Ok(())
+    }
+}
+
+/// Determines if a trigger name matches the requested one.
+///
+/// Triggers created by device drivers are usually named after the device
+/// with a "-devN" suffix, like "ads1015-dev0", so the requested name
+/// matches with or without the suffix.
+fn name_matches(trig_name: &str, name: &str) -> bool {
+    if trig_name == name {
+        return true;
+    }
+    match trig_name.rfind("-dev") {
+        Some(pos) => {
+            let n = &trig_name[pos + 4..];
+            &trig_name[..pos] == name && !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+impl AsRef<Device> for Trigger {
+    fn as_ref(&self) -> &Device {
+        &self.dev
+    }
+}
+
+impl Context {
+    /// Gets an iterator for the triggers in the context.
+    pub fn triggers(&self) -> impl Iterator<Item = Trigger> + '_ {
+        self.devices().filter_map(|dev| Trigger::from_device(dev).ok())
+    }
+
+    /// Try to find a trigger by its ID or name.
+    ///
+    /// The ID or name must match exactly, except that a trigger with a
+    /// "-devN" suffix on its name, as is common for the triggers created
+    /// by device drivers, also matches the name without the suffix. So
+    /// "ads1015" will find the trigger "ads1015-dev0".
+    pub fn find_trigger(&self, name: &str) -> Option<Trigger> {
+        self.triggers()
+            .find(|trig| trig.id().as_deref() == Some(name))
+            .or_else(|| {
+                self.triggers()
+                    .find(|trig| name_matches(&trig.name().unwrap_or_default(), name))
+            })
+    }
+}
+
+impl Device {
+    /// Associates the trigger with the specified ID or name with the
+    /// device.
+    ///
+    /// The trigger is found with [`Context::find_trigger()`], and is
+    /// returned so that it can be configured further.
+    pub fn set_trigger_by_name(&self, name: &str) -> Result<Trigger> {
+        let trig = self
+            .ctx
+            .find_trigger(name)
+            .ok_or_else(|| Error::General(format!("Trigger not found: {}", name)))?;
+        self.set_trigger(&trig)?;
+        Ok(trig)
+    }
+
+    /// Gets the trigger associated with the device, if any.
+    pub fn trigger(&self) -> Result<Option<Trigger>> {
+        let mut trig = ptr::null();
+        let ret = unsafe { ffi::iio_device_get_trigger(self.dev, &mut trig) };
+        sys_result(ret, ())?;
+
+        if trig.is_null() {
+            return Ok(None);
+        }
+        let dev = Device {
+            dev: trig as *mut _,
+            ctx: self.context(),
+        };
+        Ok(Some(Trigger { dev }))
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trigger names match with or without a "-devN" suffix.
+    #[test]
+    fn trigger_name_matches() {
+        assert!(name_matches("trigger0", "trigger0"));
+        assert!(name_matches("ads1015-dev0", "ads1015"));
+        assert!(name_matches("ads1015-dev12", "ads1015"));
+        assert!(!name_matches("ads1015-dev", "ads1015"));
+        assert!(!name_matches("ads1015-devx", "ads1015"));
+        assert!(!name_matches("ads1115-dev0", "ads1015"));
+    }
+}
@@ -0,0 +1,380 @@
+// industrial-io/src/units.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Physical units for the values read from channels.
+//!
+//! The kernel IIO ABI specifies the units of the processed value for each
+//! type of channel, after the scale and offset are applied to the raw
+//! value. Some of these are not the obvious SI units. Voltages are in
+//! millivolts, and temperatures are in millidegrees Celsius, for example.
+//!
+//! [`Channel::read_measurement()`] reads the processed value of a channel,
+//! tagged with the unit for its type, to help prevent mistakes.
+//!
+//! There are also newtypes for some of the attributes that have units,
+//! like the hardware gain of a channel, in [`Decibels`], and the sampling
+//! frequency of a device, in [`Hertz`].
+//!
+
+use std::fmt;
+
+use super::*;
+use nix::errno::Errno;
+
+/// The name of the attribute for the sampling frequency.
+const SAMPLING_FREQ_ATTR: &str = "sampling_frequency";
+
+/// The unit of a processed value, as specified by the IIO ABI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Millivolts
+    Millivolts,
+    /// Milliamps
+    Milliamps,
+    /// Milliwatts
+    Milliwatts,
+    /// Meters per second squared
+    MetersPerSecondSquared,
+    /// Radians per second
+    RadiansPerSecond,
+    /// Gauss
+    Gauss,
+    /// Lux
+    Lux,
+    /// Millidegrees Celsius
+    MillidegreesCelsius,
+    /// Degrees of angle
+    Degrees,
+    /// Radians of angle
+    Radians,
+    /// Nanoseconds
+    Nanoseconds,
+    /// Nanofarads
+    Nanofarads,
+    /// Kelvin, for a color temperature
+    Kelvin,
+    /// Kilopascals
+    Kilopascals,
+    /// Thousandths of a percent
+    MilliPercent,
+    /// Percent
+    Percent,
+    /// Joules
+    Joules,
+    /// Meters
+    Meters,
+    /// Meters per second
+    MetersPerSecond,
+    /// Ohms
+    Ohms,
+    /// The pH scale
+    Ph,
+    /// Siemens per meter
+    SiemensPerMeter,
+    /// Micrograms per cubic meter
+    MicrogramsPerCubicMeter,
+    /// Revolutions per minute
+    Rpm,
+    /// A count or index, with no unit
+    Unitless,
+}
+
+impl Unit {
+    /// Gets the symbol for the unit.
+    pub fn symbol(&self) -> &'static str {
+        use Unit::*;
+        match *self {
+            Millivolts => "mV",
+            Milliamps => "mA",
+            Milliwatts => "mW",
+            MetersPerSecondSquared => "m/s²",
+            RadiansPerSecond => "rad/s",
+            Gauss => "G",
+            Lux => "lx",
+            MillidegreesCelsius => "m°C",
+            Degrees => "°",
+            Radians => "rad",
+            Nanoseconds => "ns",
+            Nanofarads => "nF",
+            Kelvin => "K",
+            Kilopascals => "kPa",
+            MilliPercent => "m%",
+            Percent => "%",
+            Joules => "J",
+            Meters => "m",
+            MetersPerSecond => "m/s",
+            Ohms => "Ω",
+            Ph => "pH",
+            SiemensPerMeter => "S/m",
+            MicrogramsPerCubicMeter => "µg/m³",
+            Rpm => "RPM",
+            Unitless => "",
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// A gain, in decibels.
+///
+/// This is the type of the `hardwaregain` attribute of a channel. Some
+/// drivers report the value with a "dB" suffix, like "71.000000 dB", which
+/// is accepted when parsing. The value is written without the suffix.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Decibels(pub f64);
+
+impl fmt::Display for Decibels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} dB", self.0)
+    }
+}
+
+impl FromStr for Decibels {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let s = s.strip_suffix("dB").unwrap_or(s);
+        s.trim()
+            .parse()
+            .map(Decibels)
+            .map_err(|_| Error::StringConversionError)
+    }
+}
+
+impl ToAttribute for Decibels {
+    fn to_attr(&self) -> Result<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+impl FromAttribute for Decibels {}
+
+/// A frequency, in Hertz.
+///
+/// This is the type of the `sampling_frequency` attribute of a device or
+/// channel.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Hertz(pub f64);
+
+impl fmt::Display for Hertz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} Hz", self.0)
+    }
+}
+
+impl FromStr for Hertz {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let s = s.strip_suffix("Hz").unwrap_or(s);
+        s.trim()
+            .parse()
+            .map(Hertz)
+            .map_err(|_| Error::StringConversionError)
+    }
+}
+
+impl ToAttribute for Hertz {
+    fn to_attr(&self) -> Result<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+impl FromAttribute for Hertz {}
+
+impl ChannelType {
+    /// Gets the unit of the processed values for this type of channel.
+    ///
+    /// This returns `None` for the types that have no unit specified by
+    /// the ABI, like activity channels.
+    pub fn unit(&self) -> Option<Unit> {
+        use ChannelType::*;
+        let unit = match *self {
+            Voltage | AltVoltage => Unit::Millivolts,
+            Current => Unit::Milliamps,
+            Power => Unit::Milliwatts,
+            Accel | Gravity => Unit::MetersPerSecondSquared,
+            AnglVel => Unit::RadiansPerSecond,
+            Magn => Unit::Gauss,
+            Light => Unit::Lux,
+            Temp => Unit::MillidegreesCelsius,
+            Incli | Rot => Unit::Degrees,
+            Angl | Phase => Unit::Radians,
+            Timestamp => Unit::Nanoseconds,
+            Capacitance => Unit::Nanofarads,
+            Cct => Unit::Kelvin,
+            Pressure => Unit::Kilopascals,
+            HumidityRelative | PositionRelative => Unit::MilliPercent,
+            Concentration => Unit::Percent,
+            Energy => Unit::Joules,
+            Distance => Unit::Meters,
+            Velocity => Unit::MetersPerSecond,
+            Resistance => Unit::Ohms,
+            Ph => Unit::Ph,
+            ElectricalConductivity => Unit::SiemensPerMeter,
+            MassConcentration => Unit::MicrogramsPerCubicMeter,
+            Intensity | UvIndex | Steps | Count | Index => Unit::Unitless,
+            Proximity | Activity | Unknown => return None,
+        };
+        Some(unit)
+    }
+}
+
+/// A processed value read from a channel, tagged with its unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// The value, after the scale and offset have been applied
+    pub value: f64,
+    /// The unit of the value
+    pub unit: Unit,
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            Unit::Unitless => write!(f, "{}", self.value),
+            unit => write!(f, "{} {}", self.value, unit),
+        }
+    }
+}
+
+impl Channel {
+    /// Gets the hardware gain of the channel.
+    ///
+    /// This reads the `hardwaregain` attribute.
+    pub fn hardware_gain(&self) -> Result<Decibels> {
+        self.attr_read("hardwaregain")
+    }
+
+    /// Sets the hardware gain of the channel.
+    ///
+    /// If the channel has a `hardwaregain_available` attribute, the value
+    /// is checked against it first, and [`Error::ValueNotAvailable`] is
+    /// returned if the driver doesn't accept the value.
+    pub fn set_hardware_gain(&self, gain: Decibels) -> Result<()> {
+        if self.has_attr("hardwaregain_available")
+            && !self.attr_available("hardwaregain")?.contains(gain.0)
+        {
+            return Err(Error::ValueNotAvailable);
+        }
+        self.attr_write("hardwaregain", gain)
+    }
+
+    /// Reads the current value of the channel, tagged with its unit.
+    ///
+    /// This reads the value with [`Channel::read_processed()`], and tags
+    /// it with the unit for the type of the channel. If the type of the
+    /// channel has no known unit, this returns [`Error::WrongDataType`].
+    pub fn read_measurement(&self) -> Result<Measurement> {
+        let unit = self.channel_type().unit().ok_or(Error::WrongDataType)?;
+        let value = self.read_processed()?;
+        Ok(Measurement { value, unit })
+    }
+}
+
+impl Device {
+    /// Gets the sampling frequency of the device.
+    ///
+    /// This reads the `sampling_frequency` attribute of the device. If the
+    /// device doesn't have one, the attribute from the first channel that
+    /// has one is used instead.
+    pub fn sampling_frequency(&self) -> Result<Hertz> {
+        if self.has_attr(SAMPLING_FREQ_ATTR) {
+            return self.attr_read(SAMPLING_FREQ_ATTR);
+        }
+        self.channels()
+            .find(|chan| chan.has_attr(SAMPLING_FREQ_ATTR))
+            .ok_or_else(|| Error::from(Errno::ENOENT))?
+            .attr_read(SAMPLING_FREQ_ATTR)
+    }
+
+    /// Sets the sampling frequency of the device.
+    ///
+    /// This writes the `sampling_frequency` attribute of the device. If the
+    /// device doesn't have one, it is written to all the channels that do.
+    /// If there is a `sampling_frequency_available` attribute, the value is
+    /// checked against it first, and [`Error::ValueNotAvailable`] is
+    /// returned if the driver doesn't accept the value.
+    pub fn set_sampling_frequency(&self, freq: Hertz) -> Result<()> {
+        let avail = format!("{}_available", SAMPLING_FREQ_ATTR);
+
+        if self.has_attr(SAMPLING_FREQ_ATTR) {
+            if self.has_attr(&avail) && !self.attr_available(SAMPLING_FREQ_ATTR)?.contains(freq.0)
+            {
+                return Err(Error::ValueNotAvailable);
+            }
+            return self.attr_write(SAMPLING_FREQ_ATTR, freq);
+        }
+
+        let chans: Vec<_> = self
+            .channels()
+            .filter(|chan| chan.has_attr(SAMPLING_FREQ_ATTR))
+            .collect();
+        if chans.is_empty() {
+            return Err(Errno::ENOENT.into());
+        }
+        for chan in &chans {
+            if chan.has_attr(&avail) && !chan.attr_available(SAMPLING_FREQ_ATTR)?.contains(freq.0)
+            {
+                return Err(Error::ValueNotAvailable);
+            }
+        }
+        for chan in &chans {
+            chan.attr_write(SAMPLING_FREQ_ATTR, freq)?;
+        }
+        Ok(())
+    }
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The units follow the kernel ABI.
+    #[test]
+    fn channel_units() {
+        assert_eq!(ChannelType::Voltage.unit(), Some(Unit::Millivolts));
+        assert_eq!(ChannelType::Temp.unit(), Some(Unit::MillidegreesCelsius));
+        assert_eq!(ChannelType::AnglVel.unit(), Some(Unit::RadiansPerSecond));
+        assert_eq!(ChannelType::Unknown.unit(), None);
+
+        let m = Measurement {
+            value: 1.5,
+            unit: Unit::MetersPerSecondSquared,
+        };
+        assert_eq!(m.to_string(), "1.5 m/s²");
+    }
+
+    // Gains are parsed with or without the suffix.
+    #[test]
+    fn decibels() {
+        assert_eq!("71.000000 dB".parse::<Decibels>().unwrap(), Decibels(71.0));
+        assert_eq!("-3.5".parse::<Decibels>().unwrap(), Decibels(-3.5));
+        assert!("loud".parse::<Decibels>().is_err());
+        assert_eq!(Decibels(10.0).to_attr().unwrap(), "10");
+    }
+
+    // Frequencies are written as plain numbers.
+    #[test]
+    fn hertz() {
+        assert_eq!("1000.5".parse::<Hertz>().unwrap(), Hertz(1000.5));
+        assert_eq!(Hertz(100.0).to_attr().unwrap(), "100");
+        assert_eq!(Hertz(100.0).to_string(), "100 Hz");
+    }
+}
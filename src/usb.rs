@@ -0,0 +1,190 @@
+// industrial-io/src/usb.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Selection of USB contexts by their vendor, product, and serial number.
+//!
+//! The URI for a USB context, like "usb:3.32.5", holds the bus, address,
+//! and interface of the device, which can change each time it is plugged
+//! in. When several boards are attached, such as in a test rack, they can
+//! be picked deterministically by their USB IDs and serial numbers, which
+//! the C library reports in the description of each context found by a
+//! scan.
+//!
+
+use super::*;
+
+/// Information about a USB context that was found by a scan.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UsbContextInfo {
+    /// The URI to create the context, like "usb:3.32.5"
+    pub uri: String,
+    /// The USB bus number
+    pub bus: u32,
+    /// The address of the device on the bus
+    pub address: u32,
+    /// The USB interface for the IIO daemon
+    pub interface: u32,
+    /// The USB vendor ID
+    pub vid: u16,
+    /// The USB product ID
+    pub pid: u16,
+    /// The serial number of the device, if it has one
+    pub serial: Option<String>,
+    /// The description of the context, from the scan
+    pub description: String,
+}
+
+impl UsbContextInfo {
+    /// Gets the USB information from a context found by a scan, if it's
+    /// a USB context.
+    pub fn from_info(info: &ContextInfo) -> Option<Self> {
+        let (bus, address, interface) = parse_usb_uri(&info.uri)?;
+        let (vid, pid, serial) = parse_usb_description(&info.description)?;
+        Some(Self {
+            uri: info.uri.clone(),
+            bus,
+            address,
+            interface,
+            vid,
+            pid,
+            serial,
+            description: info.description.clone(),
+        })
+    }
+
+    /// Determines if the device has the IDs and, if given, the serial
+    /// number.
+    pub fn matches(&self, vid: u16, pid: u16, serial: Option<&str>) -> bool {
+        self.vid == vid
+            && self.pid == pid
+            && serial.map_or(true, |s| self.serial.as_deref() == Some(s))
+    }
+
+    /// Creates the context.
+    pub fn create_context(&self) -> Result<Context> {
+        Context::from_uri(&self.uri)
+    }
+}
+
+/// Scans for the available USB contexts.
+///
+/// The contexts are sorted by their bus, address, and interface, so that
+/// the order is the same from one scan to the next.
+pub fn scan_usb_contexts() -> Result<Vec<UsbContextInfo>> {
+    let mut infos: Vec<_> = scan_contexts(Some("usb"))?
+        .iter()
+        .filter_map(UsbContextInfo::from_info)
+        .collect();
+    infos.sort_by_key(|info| (info.bus, info.address, info.interface));
+    Ok(infos)
+}
+
+/// Scans for the USB contexts with the IDs and, if given, the serial
+/// number.
+pub fn find_usb_contexts(vid: u16, pid: u16, serial: Option<&str>) -> Result<Vec<UsbContextInfo>> {
+    let mut infos = scan_usb_contexts()?;
+    infos.retain(|info| info.matches(vid, pid, serial));
+    Ok(infos)
+}
+
+impl Context {
+    /// Creates a context for the USB device with the IDs and, if given,
+    /// the serial number.
+    ///
+    /// This fails if no device matches, or if more than one does, so that
+    /// the wrong board is never picked by accident. Give the serial number
+    /// to choose between several boards of the same type.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use industrial_io as iio;
+    ///
+    /// let ctx = iio::Context::from_usb(0x0456, 0xb673, Some("104473541196000")).unwrap();
+    /// ```
+    pub fn from_usb(vid: u16, pid: u16, serial: Option<&str>) -> Result<Self> {
+        let mut infos = find_usb_contexts(vid, pid, serial)?;
+
+        let id = match serial {
+            Some(s) => format!("{:04x}:{:04x}, serial={}", vid, pid, s),
+            None => format!("{:04x}:{:04x}", vid, pid),
+        };
+
+        match infos.len() {
+            0 => Err(Error::General(format!("USB device not found: {}", id))),
+            1 => infos.remove(0).create_context(),
+            n => Err(Error::General(format!("{} USB devices match: {}", n, id))),
+        }
+    }
+}
+
+/// Parses the bus, address, and interface from a USB URI, like
+/// "usb:3.32.5".
+fn parse_usb_uri(uri: &str) -> Option<(u32, u32, u32)> {
+    let mut it = uri.strip_prefix("usb:")?.split('.').map(|s| s.parse().ok());
+    let bus = it.next()??;
+    let address = it.next()??;
+    let interface = it.next()??;
+    if it.next().is_some() {
+        return None;
+    }
+    Some((bus, address, interface))
+}
+
+/// Parses the IDs and serial number from the description of a USB context.
+///
+/// The C library describes them like:
+/// "0456:b673 (Analog Devices Inc. PlutoSDR (ADALM-PLUTO)), serial=1044..."
+fn parse_usb_description(desc: &str) -> Option<(u16, u16, Option<String>)> {
+    let ids = desc.split_whitespace().next()?;
+    let mut it = ids.splitn(2, ':');
+    let vid = u16::from_str_radix(it.next()?, 16).ok()?;
+    let pid = u16::from_str_radix(it.next()?, 16).ok()?;
+
+    let serial = desc
+        .rfind("serial=")
+        .map(|i| desc[i + 7..].trim().to_string())
+        .filter(|s| !s.is_empty());
+    Some((vid, pid, serial))
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // USB contexts are identified from the URI and description of a scan.
+    #[test]
+    fn usb_context_info() {
+        let info = ContextInfo {
+            uri: "usb:3.32.5".to_string(),
+            description: "0456:b673 (Analog Devices Inc. PlutoSDR (ADALM-PLUTO)), \
+                          serial=104473541196000"
+                .to_string(),
+        };
+        let usb = UsbContextInfo::from_info(&info).unwrap();
+        assert_eq!((usb.bus, usb.address, usb.interface), (3, 32, 5));
+        assert_eq!((usb.vid, usb.pid), (0x0456, 0xb673));
+        assert_eq!(usb.serial.as_deref(), Some("104473541196000"));
+
+        assert!(usb.matches(0x0456, 0xb673, None));
+        assert!(usb.matches(0x0456, 0xb673, Some("104473541196000")));
+        assert!(!usb.matches(0x0456, 0xb673, Some("104473541196001")));
+        assert!(!usb.matches(0x0456, 0xb672, None));
+
+        let info = ContextInfo {
+            uri: "ip:192.168.2.1".to_string(),
+            description: "192.168.2.1 (pluto)".to_string(),
+        };
+        assert!(UsbContextInfo::from_info(&info).is_none());
+    }
+}
@@ -0,0 +1,223 @@
+// industrial-io/src/watch.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Watchers for changes to the values of attributes.
+//!
+//! An [`AttrWatcher`] monitors a set of device and channel attributes, and
+//! reports any that have changed, such as a gain or frequency that was
+//! modified by another application. It can be polled directly, or run on
+//! a background thread that delivers the changes through a channel or a
+//! callback.
+//!
+//! The attributes are polled at a fixed period. Even with the local
+//! backend, the kernel doesn't generate inotify events when the value of a
+//! sysfs attribute changes, so polling is the only reliable option.
+//!
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use super::*;
+
+/// An attribute that is monitored by a watcher.
+#[derive(Debug, Clone)]
+pub enum WatchedAttr {
+    /// A device-specific attribute
+    Device(Device, String),
+    /// A channel-specific attribute
+    Channel(Channel, String),
+}
+
+impl WatchedAttr {
+    /// Gets the name of the attribute.
+    pub fn name(&self) -> &str {
+        match self {
+            WatchedAttr::Device(_, attr) | WatchedAttr::Channel(_, attr) => attr,
+        }
+    }
+
+    /// Reads the current value of the attribute.
+    pub fn read(&self) -> Result<String> {
+        match self {
+            WatchedAttr::Device(dev, attr) => dev.attr_read_str(attr),
+            WatchedAttr::Channel(chan, attr) => chan.attr_read_str(attr),
+        }
+    }
+}
+
+/// A change to the value of a watched attribute.
+#[derive(Debug, Clone)]
+pub struct AttrChange {
+    /// The attribute that changed
+    pub attr: WatchedAttr,
+    /// The previous value of the attribute
+    pub old: String,
+    /// The new value of the attribute
+    pub new: String,
+}
+
+/// A watcher that polls a set of attributes for changes.
+///
+/// The first poll reads the initial values of the attributes. Each poll
+/// after that reports the attributes whose values have changed since the
+/// previous one. An attribute that can't be read is skipped, and keeps its
+/// last known value.
+#[derive(Debug, Clone)]
+pub struct AttrWatcher {
+    /// The attributes to watch, with their last known values
+    attrs: Vec<(WatchedAttr, Option<String>)>,
+    /// The time between polls, when run on a thread
+    period: Duration,
+}
+
+impl AttrWatcher {
+    /// Creates a watcher that polls at the specified period when it is
+    /// run on a thread.
+    pub fn new(period: Duration) -> Self {
+        Self {
+            attrs: Vec::new(),
+            period,
+        }
+    }
+
+    /// Adds a device-specific attribute to watch.
+    pub fn device_attr(mut self, dev: &Device, attr: &str) -> Self {
+        self.attrs.push((WatchedAttr::Device(dev.clone(), attr.into()), None));
+        self
+    }
+
+    /// Adds a channel-specific attribute to watch.
+    pub fn channel_attr(mut self, chan: &Channel, attr: &str) -> Self {
+        self.attrs.push((WatchedAttr::Channel(chan.clone(), attr.into()), None));
+        self
+    }
+
+    /// Reads all the attributes, and returns the ones that have changed
+    /// since the last poll.
+    pub fn poll(&mut self) -> Vec<AttrChange> {
+        let mut changes = Vec::new();
+        for (attr, last) in self.attrs.iter_mut() {
+            let val = match attr.read() {
+                Ok(val) => val,
+                Err(_) => continue,
+            };
+            match last.replace(val.clone()) {
+                Some(old) if old != val => changes.push(AttrChange {
+                    attr: attr.clone(),
+                    old,
+                    new: val,
+                }),
+                _ => (),
+            }
+        }
+        changes
+    }
+
+    /// Runs the watcher on a background thread, sending the changes to
+    /// the returned receiver.
+    ///
+    /// The thread stops when the handle is dropped, or when the receiver
+    /// is dropped.
+    pub fn spawn(self) -> (WatchHandle, mpsc::Receiver<AttrChange>) {
+        let (tx, rx) = mpsc::channel();
+        let handle = self.spawn_inner(move |change| tx.send(change).is_ok());
+        (handle, rx)
+    }
+
+    /// Runs the watcher on a background thread, calling the callback for
+    /// each change.
+    ///
+    /// The thread stops when the handle is dropped.
+    pub fn spawn_with_callback<F>(self, mut f: F) -> WatchHandle
+    where
+        F: FnMut(AttrChange) + Send + 'static,
+    {
+        self.spawn_inner(move |change| {
+            f(change);
+            true
+        })
+    }
+
+    /// Runs the watcher on a thread, passing each change to the function,
+    /// until it returns `false` or the handle signals a stop.
+    fn spawn_inner<F>(mut self, mut f: F) -> WatchHandle
+    where
+        F: FnMut(AttrChange) -> bool + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thr_stop = Arc::clone(&stop);
+
+        let thr = thread::spawn(move || {
+            self.poll();
+            while !thr_stop.load(Ordering::SeqCst) {
+                thread::park_timeout(self.period);
+                if thr_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                for change in self.poll() {
+                    if !f(change) {
+                        return;
+                    }
+                }
+            }
+        });
+
+        WatchHandle::new(stop, thr)
+    }
+}
+
+/// A handle to a watcher running on a background thread.
+///
+/// The thread is stopped when the handle is dropped.
+#[derive(Debug)]
+pub struct WatchHandle {
+    /// Flag to signal the thread to stop
+    stop: Arc<AtomicBool>,
+    /// The watcher thread
+    thr: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Creates a handle for a thread that polls the stop flag.
+    ///
+    /// The thread is unparked when it is signaled to stop.
+    pub(crate) fn new(stop: Arc<AtomicBool>, thr: JoinHandle<()>) -> Self {
+        Self {
+            stop,
+            thr: Some(thr),
+        }
+    }
+
+    /// Stops the watcher, and waits for the thread to exit.
+    pub fn stop(mut self) {
+        self.stop_thread();
+    }
+
+    /// Signals the thread to stop, and joins it.
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thr) = self.thr.take() {
+            thr.thread().unpark();
+            let _ = thr.join();
+        }
+    }
+}
+
+/// Stops the watcher thread when the handle is dropped.
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
@@ -0,0 +1,414 @@
+// industrial-io/src/zeroconf.rs
+//
+// Copyright (c) 2018-2021, Frank Pagliughi
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+//
+//! Discovery of the IIO daemons on the local network, with zeroconf.
+//!
+//! The IIO daemon announces itself with DNS service discovery over
+//! multicast DNS (mDNS), as the `_iio._tcp` service, typically through
+//! Avahi. This sends a one-shot mDNS query for the service and collects
+//! the answers, without needing Avahi or any other service on the local
+//! host. Each daemon is reported with its host name, addresses, port, and
+//! TXT records, ready to create a network context.
+//!
+//! Only IPv4 multicast is used to send the query, although the answers
+//! can contain IPv6 addresses.
+//!
+//! This requires the `zeroconf` feature.
+//!
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    net::{IpAddr, Ipv4Addr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use super::*;
+
+/// The DNS-SD service type of the IIO daemon
+pub const IIOD_SERVICE: &str = "_iio._tcp.local";
+
+/// The multicast group for mDNS
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// The UDP port for mDNS
+const MDNS_PORT: u16 = 5353;
+
+// The DNS record types and class that are used for discovery.
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// An IIO daemon that was found on the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredHost {
+    /// The name of the service instance, like "iio on pluto"
+    pub name: String,
+    /// The host name, like "pluto.local"
+    pub hostname: String,
+    /// The addresses of the host
+    pub addrs: Vec<IpAddr>,
+    /// The TCP port of the daemon
+    pub port: u16,
+    /// The TXT records of the service, as keys and values
+    pub txt: BTreeMap<String, String>,
+}
+
+impl DiscoveredHost {
+    /// Gets the preferred address of the host, if any are known.
+    ///
+    /// This is the first IPv4 address, if there is one, since IPv6
+    /// link-local addresses can't be used without an interface.
+    pub fn address(&self) -> Option<IpAddr> {
+        self.addrs.iter().find(|addr| addr.is_ipv4()).or_else(|| self.addrs.first()).copied()
+    }
+
+    /// Gets the host to connect to, preferring an address to the name.
+    fn host(&self) -> String {
+        self.address().map_or_else(|| self.hostname.clone(), |addr| addr.to_string())
+    }
+
+    /// Gets the URI for a network context to the daemon, like
+    /// "ip:192.168.2.1:30431".
+    pub fn uri(&self) -> String {
+        format!("ip:{}", network_host(&self.host(), self.port))
+    }
+
+    /// Creates a network context to the daemon.
+    pub fn create_context(&self) -> Result<Context> {
+        Context::from_network_port(&self.host(), self.port)
+    }
+}
+
+/// Discovers the IIO daemons on the local network.
+///
+/// This sends an mDNS query for the IIO service, and collects the answers
+/// until the timeout expires.
+///
+/// # Examples
+///
+/// ```no_run
+/// use industrial_io as iio;
+/// use std::time::Duration;
+///
+/// for host in iio::discover_iiod(Duration::from_secs(1)).unwrap() {
+///     println!("{} at {}", host.hostname, host.uri());
+/// }
+/// ```
+pub fn discover_iiod(timeout: Duration) -> Result<Vec<DiscoveredHost>> {
+    let sock = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    sock.send_to(&ptr_query(IIOD_SERVICE), (MDNS_GROUP, MDNS_PORT))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut resp = Responses::default();
+    let mut buf = [0u8; 9000];
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        sock.set_read_timeout(Some(deadline - now))?;
+
+        match sock.recv_from(&mut buf) {
+            Ok((n, src)) => {
+                if let Some(recs) = parse_records(&buf[..n]) {
+                    resp.add(recs, src.ip());
+                }
+            }
+            Err(err) => {
+                let kind = err.kind();
+                if kind == io::ErrorKind::WouldBlock || kind == io::ErrorKind::TimedOut {
+                    break;
+                }
+                return Err(err.into());
+            }
+        }
+    }
+    Ok(resp.hosts(IIOD_SERVICE))
+}
+
+/// A DNS resource record that is used for discovery.
+#[derive(Debug, Clone, PartialEq)]
+enum Record {
+    /// A service type name, and the name of an instance of it
+    Ptr(String, String),
+    /// An instance name, and the port and host name of the service
+    Srv(String, u16, String),
+    /// An instance name, and the TXT strings of the service
+    Txt(String, Vec<String>),
+    /// A host name, and one of its addresses
+    Addr(String, IpAddr),
+}
+
+/// The records that were collected from the answers to a query.
+///
+/// The names are kept in lower case, since DNS names aren't case
+/// sensitive.
+#[derive(Debug, Default)]
+struct Responses {
+    /// The names of the service instances, in the order found
+    instances: Vec<String>,
+    /// The port and host name of each instance
+    srv: HashMap<String, (u16, String)>,
+    /// The TXT strings of each instance
+    txt: HashMap<String, Vec<String>>,
+    /// The addresses of each host
+    addrs: HashMap<String, Vec<IpAddr>>,
+}
+
+impl Responses {
+    /// Adds the records from an answer that was sent from `src`.
+    ///
+    /// The sender is taken as an address of the hosts in the answer, in
+    /// case it has no address records.
+    fn add(&mut self, recs: Vec<Record>, src: IpAddr) {
+        for rec in recs {
+            match rec {
+                Record::Ptr(_, inst) => {
+                    if !self.instances.iter().any(|s| s.eq_ignore_ascii_case(&inst)) {
+                        self.instances.push(inst);
+                    }
+                }
+                Record::Srv(inst, port, host) => {
+                    let host = host.to_ascii_lowercase();
+                    self.add_addr(&host, src);
+                    self.srv.insert(inst.to_ascii_lowercase(), (port, host));
+                }
+                Record::Txt(inst, strs) => {
+                    self.txt.insert(inst.to_ascii_lowercase(), strs);
+                }
+                Record::Addr(host, addr) => self.add_addr(&host.to_ascii_lowercase(), addr),
+            }
+        }
+    }
+
+    /// Adds an address for a host, if it's not already known.
+    fn add_addr(&mut self, host: &str, addr: IpAddr) {
+        let addrs = self.addrs.entry(host.to_string()).or_default();
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+
+    /// Gets the instances of the service that have a host and port.
+    fn hosts(&self, service: &str) -> Vec<DiscoveredHost> {
+        self.instances
+            .iter()
+            .filter_map(|inst| {
+                let key = inst.to_ascii_lowercase();
+                let (port, host) = self.srv.get(&key)?;
+
+                let name = key
+                    .strip_suffix(&service.to_ascii_lowercase())
+                    .map_or(inst.as_str(), |s| &inst[..s.len()])
+                    .trim_end_matches('.')
+                    .to_string();
+
+                Some(DiscoveredHost {
+                    name,
+                    hostname: host.trim_end_matches('.').to_string(),
+                    addrs: self.addrs.get(host).cloned().unwrap_or_default(),
+                    port: *port,
+                    txt: self.txt.get(&key).map(|strs| txt_map(strs)).unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Creates an mDNS query for the instances of a service.
+fn ptr_query(service: &str) -> Vec<u8> {
+    // The header, with a single question
+    let mut msg = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in service.split('.').filter(|s| !s.is_empty()) {
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+    msg
+}
+
+/// Reads a big-endian 16-bit value from a DNS message.
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    let b = buf.get(pos..pos + 2)?;
+    Some(u16::from_be_bytes([b[0], b[1]]))
+}
+
+/// Reads a name from a DNS message, following any compression pointers.
+///
+/// Returns the name, and the position just past it in the message.
+fn read_name(buf: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+
+    // Limit the number of labels and pointers, in case of a loop
+    for _ in 0..128 {
+        let len = *buf.get(pos)? as usize;
+        if len & 0xC0 == 0xC0 {
+            end.get_or_insert(pos + 2);
+            pos = (read_u16(buf, pos)? & 0x3FFF) as usize;
+        }
+        else if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        else {
+            let label = buf.get(pos + 1..pos + 1 + len)?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += len + 1;
+        }
+    }
+    None
+}
+
+/// Splits the data of a TXT record into its strings.
+fn txt_strings(mut data: &[u8]) -> Vec<String> {
+    let mut strs = Vec::new();
+    while let Some((&len, rest)) = data.split_first() {
+        let len = (len as usize).min(rest.len());
+        if len > 0 {
+            strs.push(String::from_utf8_lossy(&rest[..len]).into_owned());
+        }
+        data = &rest[len..];
+    }
+    strs
+}
+
+/// Splits the TXT strings of a service into keys and values.
+///
+/// A string without an '=', which is a boolean attribute, gets an empty
+/// value.
+fn txt_map(strs: &[String]) -> BTreeMap<String, String> {
+    strs.iter()
+        .map(|s| {
+            let mut kv = s.splitn(2, '=');
+            let key = kv.next().unwrap_or_default().to_string();
+            (key, kv.next().unwrap_or_default().to_string())
+        })
+        .collect()
+}
+
+/// Parses the records used for discovery from a DNS response.
+///
+/// Returns `None` if the message is not a response or is malformed.
+fn parse_records(buf: &[u8]) -> Option<Vec<Record>> {
+    if read_u16(buf, 2)? & 0x8000 == 0 {
+        return None;
+    }
+
+    let nquest = read_u16(buf, 4)?;
+    let nrec = [6, 8, 10]
+        .iter()
+        .map(|&pos| read_u16(buf, pos).map(usize::from))
+        .sum::<Option<usize>>()?;
+
+    let mut pos = 12;
+    for _ in 0..nquest {
+        pos = read_name(buf, pos)?.1 + 4;
+    }
+
+    let mut recs = Vec::new();
+    for _ in 0..nrec {
+        let (name, p) = read_name(buf, pos)?;
+        let typ = read_u16(buf, p)?;
+        let len = read_u16(buf, p + 8)? as usize;
+        let start = p + 10;
+        let data = buf.get(start..start + len)?;
+        pos = start + len;
+
+        let rec = match typ {
+            TYPE_PTR => Record::Ptr(name, read_name(buf, start)?.0),
+            TYPE_SRV => {
+                let port = read_u16(buf, start + 4)?;
+                Record::Srv(name, port, read_name(buf, start + 6)?.0)
+            }
+            TYPE_TXT => Record::Txt(name, txt_strings(data)),
+            TYPE_A if len == 4 => {
+                Record::Addr(name, IpAddr::from([data[0], data[1], data[2], data[3]]))
+            }
+            TYPE_AAAA if len == 16 => {
+                let mut addr = [0u8; 16];
+                addr.copy_from_slice(data);
+                Record::Addr(name, IpAddr::from(addr))
+            }
+            _ => continue,
+        };
+        recs.push(rec);
+    }
+    Some(recs)
+}
+
+// --------------------------------------------------------------------------
+//                              Unit Tests
+// --------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Appends a resource record, with a compression pointer for its name.
+    fn push_record(msg: &mut Vec<u8>, name_ptr: u16, typ: u16, data: &[u8]) {
+        msg.extend_from_slice(&(0xC000 | name_ptr).to_be_bytes());
+        msg.extend_from_slice(&typ.to_be_bytes());
+        msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+        msg.extend_from_slice(&120u32.to_be_bytes());
+        msg.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        msg.extend_from_slice(data);
+    }
+
+    // A response with the records for a daemon is turned into a host.
+    #[test]
+    fn discovered_host() {
+        // Start with the query, echoed back as the question
+        let mut msg = ptr_query(IIOD_SERVICE);
+        msg[2] = 0x84;
+        msg[7] = 4;
+
+        // The PTR record names the instance, "iio on pluto"
+        let mut ptr = vec![12];
+        ptr.extend_from_slice(b"iio on pluto");
+        ptr.extend_from_slice(&[0xC0, 12]);
+        let inst = msg.len() as u16 + 12;
+        push_record(&mut msg, 12, TYPE_PTR, &ptr);
+
+        // The SRV record gives the port and host, "pluto.local"
+        let mut srv = vec![0, 0, 0, 0, 0x76, 0xDF, 5];
+        srv.extend_from_slice(b"pluto");
+        let local: u16 = 12 + 1 + 4 + 1 + 4;
+        srv.extend_from_slice(&(0xC000 | local).to_be_bytes());
+        let host = msg.len() as u16 + 12 + 6;
+        push_record(&mut msg, inst, TYPE_SRV, &srv);
+
+        push_record(&mut msg, inst, TYPE_TXT, b"\x0curi=ip:pluto\x05local");
+        push_record(&mut msg, host, TYPE_A, &[192, 168, 2, 1]);
+
+        let mut resp = Responses::default();
+        resp.add(parse_records(&msg).unwrap(), IpAddr::from([192, 168, 2, 1]));
+
+        let hosts = resp.hosts(IIOD_SERVICE);
+        assert_eq!(hosts.len(), 1);
+
+        let host = &hosts[0];
+        assert_eq!(host.name, "iio on pluto");
+        assert_eq!(host.hostname, "pluto.local");
+        assert_eq!(host.addrs, vec![IpAddr::from([192, 168, 2, 1])]);
+        assert_eq!(host.port, DEFAULT_IIOD_PORT);
+        assert_eq!(host.txt.get("uri").map(String::as_str), Some("ip:pluto"));
+        assert_eq!(host.txt.get("local").map(String::as_str), Some(""));
+        assert_eq!(host.uri(), "ip:192.168.2.1:30431");
+
+        // Queries are not responses
+        assert!(parse_records(&ptr_query(IIOD_SERVICE)).is_none());
+    }
+}